@@ -8,9 +8,13 @@
 //! The absolute path type, the root type for _most_ `Path*` types in this module
 //! (except for `PathArc`).
 use std::fmt;
+use std::fs;
+use std::io;
+use std::convert::TryFrom;
+use std::str::FromStr;
 use std_prelude::*;
 
-use super::{PathArc, PathDir, PathFile, Result};
+use super::{Error, PathArc, PathDir, PathFile, Result};
 
 #[derive(Clone, Eq, Hash, PartialEq, PartialOrd, Ord)]
 /// An absolute ([canonicalized][1]) path that is guaranteed (when created) to exist.
@@ -61,12 +65,72 @@ impl PathAbs {
     /// # Ok(()) } fn main() { try_main().unwrap() }
     /// ```
     pub fn parent_dir(&self) -> Option<PathDir> {
-        match self.parent() {
-            Some(p) => Some(PathDir(PathAbs(PathArc::new(p)))),
-            None => None,
+        self.parent().map(|p| PathDir(PathAbs(PathArc::new(p))))
+    }
+
+    /// Iterate over the ancestor directories of this path, starting with the parent and ending
+    /// with the root.
+    ///
+    /// > Like [`parent_dir`](#method.parent_dir), this does not make any syscall.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let lib = PathFile::new("src/lib.rs")?;
+    /// let ancestors: Vec<PathDir> = lib.ancestors_dirs().collect();
+    /// assert_eq!(PathDir::new("src")?, ancestors[0]);
+    /// assert_eq!(PathDir::current_dir()?, ancestors[1]);
+    /// assert_eq!(None, ancestors.last().unwrap().parent());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn ancestors_dirs(&self) -> AncestorDirs {
+        AncestorDirs {
+            path: Some(self.0.clone()),
         }
     }
 
+    /// Find the closest directory containing an entry called `name`, starting with this path
+    /// (if it is a directory) and then going up its ancestors. This is useful for locating
+    /// the root of a project, i.e. the directory containing `Cargo.toml` or `.git`.
+    ///
+    /// Returns `None` if no such directory exists. Errors other than the entry not existing,
+    /// i.e. a directory which can't be read, are returned instead of searching further up.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let root = PathDir::current_dir()?;
+    /// let lib = PathFile::new("src/lib.rs")?;
+    /// assert_eq!(Some(root.clone()), lib.find_ancestor_containing("Cargo.toml")?);
+    /// assert_eq!(Some(root.clone()), root.find_ancestor_containing("Cargo.toml")?);
+    /// assert_eq!(None, lib.find_ancestor_containing("does-not-exist.toml")?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn find_ancestor_containing<P: AsRef<Path>>(&self, name: P) -> Result<Option<PathDir>> {
+        let name = name.as_ref();
+        let this = match fs::metadata(self) {
+            Ok(ref meta) if meta.is_dir() => Some(PathDir(self.clone())),
+            Ok(_) => None,
+            Err(ref err) if is_absent(err) => None,
+            Err(err) => return Err(Error::new(err, "getting metadata of", self.0.clone())),
+        };
+        for dir in this.into_iter().chain(self.ancestors_dirs()) {
+            let entry = dir.join(name);
+            match entry.symlink_metadata() {
+                Ok(_) => return Ok(Some(dir)),
+                Err(ref err) if is_absent(err.io_error()) => {}
+                Err(err) => return Err(err.context("searching ancestors of", self)),
+            }
+        }
+        Ok(None)
+    }
+
     /// Return a reference to a basic `std::path::Path`
     pub fn as_path(&self) -> &Path {
         self.as_ref()
@@ -104,6 +168,22 @@ impl PathAbs {
     }
 }
 
+/// An iterator over the ancestor directories of a path, returned by
+/// [`PathAbs::ancestors_dirs`](struct.PathAbs.html#method.ancestors_dirs).
+#[derive(Debug, Clone)]
+pub struct AncestorDirs {
+    path: Option<PathArc>,
+}
+
+impl ::std::iter::Iterator for AncestorDirs {
+    type Item = PathDir;
+    fn next(&mut self) -> Option<PathDir> {
+        let parent = self.path.take()?.parent().map(PathArc::new)?;
+        self.path = Some(parent.clone());
+        Some(PathDir(PathAbs(parent)))
+    }
+}
+
 impl fmt::Debug for PathAbs {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         self.0.fmt(f)
@@ -146,19 +226,19 @@ impl Borrow<PathBuf> for PathAbs {
     }
 }
 
-impl<'a> Borrow<PathArc> for &'a PathAbs {
+impl Borrow<PathArc> for &PathAbs {
     fn borrow(&self) -> &PathArc {
         self.as_ref()
     }
 }
 
-impl<'a> Borrow<Path> for &'a PathAbs {
+impl Borrow<Path> for &PathAbs {
     fn borrow(&self) -> &Path {
         self.as_ref()
     }
 }
 
-impl<'a> Borrow<PathBuf> for &'a PathAbs {
+impl Borrow<PathBuf> for &PathAbs {
     fn borrow(&self) -> &PathBuf {
         self.as_ref()
     }
@@ -172,8 +252,106 @@ impl Deref for PathAbs {
     }
 }
 
-impl Into<PathArc> for PathAbs {
-    fn into(self) -> PathArc {
-        self.0
+impl From<PathAbs> for PathArc {
+    fn from(path: PathAbs) -> PathArc {
+        path.0
+    }
+}
+
+impl FromStr for PathAbs {
+    type Err = Error;
+
+    /// Equivalent to [`PathAbs::new`](#method.new).
+    fn from_str(s: &str) -> Result<PathAbs> {
+        PathAbs::new(s)
+    }
+}
+
+impl<'a> TryFrom<&'a Path> for PathAbs {
+    type Error = Error;
+
+    fn try_from(path: &'a Path) -> Result<PathAbs> {
+        PathAbs::new(path)
+    }
+}
+
+impl TryFrom<PathBuf> for PathAbs {
+    type Error = Error;
+
+    fn try_from(path: PathBuf) -> Result<PathAbs> {
+        PathAbs::new(path)
+    }
+}
+
+impl TryFrom<PathArc> for PathAbs {
+    type Error = Error;
+
+    fn try_from(path: PathArc) -> Result<PathAbs> {
+        PathAbs::new(path)
+    }
+}
+
+/// Whether the error means that the path does not exist, including when one of its parents is
+/// not a directory.
+fn is_absent(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::NotFound || is_not_a_directory(err)
+}
+
+#[cfg(unix)]
+fn is_not_a_directory(err: &io::Error) -> bool {
+    use libc;
+    err.raw_os_error() == Some(libc::ENOTDIR)
+}
+
+#[cfg(not(unix))]
+fn is_not_a_directory(_err: &io::Error) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::super::{PathDir, PathFile};
+
+    #[test]
+    fn find_ancestor_containing() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let project = PathDir::create(tmp_abs.join("project")).unwrap();
+        PathFile::create(project.join("Cargo.toml")).unwrap();
+        let src = PathDir::create_all(project.join("src/bin")).unwrap();
+        let main = PathFile::create(src.join("main.rs")).unwrap();
+
+        assert_eq!(Some(project.clone()), main.find_ancestor_containing("Cargo.toml").unwrap());
+        assert_eq!(Some(project.clone()), project.find_ancestor_containing("Cargo.toml").unwrap());
+        // a file is never returned, nothing can be inside of it
+        assert_eq!(Some(src.clone()), main.find_ancestor_containing("main.rs").unwrap());
+        // nested names whose parent is a file don't exist
+        assert_eq!(None, project.find_ancestor_containing("Cargo.toml/foo").unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn find_ancestor_containing_unreadable() {
+        use std::fs;
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let outer = PathDir::create(tmp_abs.join("outer")).unwrap();
+        PathDir::create(outer.join(".git")).unwrap();
+        let inner = PathDir::create(outer.join("inner")).unwrap();
+        let sub = PathDir::create(inner.join("sub")).unwrap();
+
+        // an unreadable dir is an error instead of finding the outer `.git`
+        fs::set_permissions(&inner, fs::Permissions::from_mode(0o000)).unwrap();
+        let searchable = fs::read_dir(&inner).is_ok();
+        let found = sub.find_ancestor_containing(".git");
+        fs::set_permissions(&inner, fs::Permissions::from_mode(0o755)).unwrap();
+        if !searchable {
+            // not running as root
+            assert!(found.is_err());
+        }
     }
 }
@@ -10,11 +10,13 @@
 use std::fmt;
 use std::fs;
 use std::ffi::OsStr;
+use std::convert::Infallible;
+use std::str::FromStr;
 use std_prelude::*;
 
 use super::{Error, Result};
 use abs::PathAbs;
-use dir::{ListDir, PathDir};
+use super::{ListDir, PathDir};
 
 #[derive(Clone, Eq, Hash, PartialEq, PartialOrd, Ord)]
 /// A `PathBuf` that is atomically reference counted and reimplements the `PathBuf`
@@ -22,6 +24,9 @@ use dir::{ListDir, PathDir};
 ///
 /// This is the root type of all other `Path*` types in this crate.
 ///
+/// Equal paths can share a single allocation by using a
+/// [`PathInterner`](struct.PathInterner.html), which also makes comparing them cheap.
+///
 /// This type is also serializable when the `serialize` feature is enabled.
 pub struct PathArc(pub(crate) Arc<PathBuf>);
 
@@ -42,6 +47,22 @@ impl PathArc {
         PathArc::from(path.as_ref().to_path_buf())
     }
 
+    /// Returns true if both `PathArc`s share the same allocation, i.e. because they were
+    /// cloned from each other or interned.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// use path_abs::PathArc;
+    ///
+    /// let path = PathArc::new("some/path");
+    /// assert!(path.ptr_eq(&path.clone()));
+    /// assert!(!path.ptr_eq(&PathArc::new("some/path")));
+    /// ```
+    pub fn ptr_eq(&self, other: &PathArc) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
     /// Creates an owned PathBuf with path adjoined to self.
     ///
     /// This function is identical to [std::path::PathBuf::join][0] except
@@ -178,13 +199,13 @@ impl Borrow<PathBuf> for PathArc {
     }
 }
 
-impl<'a> Borrow<Path> for &'a PathArc {
+impl Borrow<Path> for &PathArc {
     fn borrow(&self) -> &Path {
         self.as_ref()
     }
 }
 
-impl<'a> Borrow<PathBuf> for &'a PathArc {
+impl Borrow<PathBuf> for &PathArc {
     fn borrow(&self) -> &PathBuf {
         self.as_ref()
     }
@@ -205,7 +226,7 @@ impl From<PathBuf> for PathArc {
     }
 }
 
-impl Into<PathBuf> for PathArc {
+impl From<PathArc> for PathBuf {
     /// If there is only one reference to the `PathArc`, returns
     /// the inner `PathBuf`. Otherwise clones the inner `PathBuf`.
     ///
@@ -223,10 +244,30 @@ impl Into<PathBuf> for PathArc {
     /// let foo: PathBuf = base.join("foo.txt").into();
     /// # Ok(()) } fn main() { try_main().unwrap() }
     /// ```
-    fn into(self) -> PathBuf {
-        match Arc::try_unwrap(self.0) {
+    fn from(path: PathArc) -> PathBuf {
+        match Arc::try_unwrap(path.0) {
             Ok(p) => p,
             Err(inner) => inner.as_ref().clone(),
         }
     }
 }
+
+impl FromStr for PathArc {
+    type Err = Infallible;
+
+    /// Instantiate a new `PathArc` from a string. This never fails.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate path_abs;
+    /// use path_abs::PathArc;
+    ///
+    /// # fn main() {
+    /// let path: PathArc = "src/lib.rs".parse().unwrap();
+    /// assert_eq!(PathArc::new("src/lib.rs"), path);
+    /// # }
+    /// ```
+    fn from_str(s: &str) -> ::std::result::Result<PathArc, Infallible> {
+        Ok(PathArc::new(s))
+    }
+}
@@ -0,0 +1,355 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Buffered open file paths.
+
+use std::fmt;
+use std::io;
+use std_prelude::*;
+
+use super::{Error, Result};
+use super::{FileRead, FileWrite};
+use super::open::FileOpen;
+
+/// The raw file, without any error annotation. The buffered types annotate the errors
+/// themselves so that they are only annotated once.
+struct Raw(FileOpen);
+
+impl io::Read for Raw {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.file.read(buf)
+    }
+}
+
+impl io::Write for Raw {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.file.flush()
+    }
+}
+
+impl io::Seek for Raw {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        self.0.file.seek(pos)
+    }
+}
+
+/// A buffered read-only file handle with `path()` attached and improved error messages,
+/// returned by [`FileRead::buffered`](struct.FileRead.html#method.buffered).
+///
+/// This is the equivalent of `std::io::BufReader<FileRead>`, except that the path is still
+/// accessible and all errors include it.
+///
+/// # Examples
+/// ```rust
+/// # extern crate path_abs;
+/// # extern crate tempdir;
+/// use std::io::BufRead;
+/// use path_abs::PathFile;
+///
+/// # fn try_main() -> ::std::io::Result<()> {
+/// let example = "example.txt";
+/// # let tmp = tempdir::TempDir::new("ex")?;
+/// # let example = &tmp.path().join(example);
+/// let file = PathFile::create(example)?;
+/// file.write_str("foo\nbar\n")?;
+///
+/// let read = file.read()?.buffered();
+/// let lines = read.lines().collect::<Result<Vec<_>, _>>()?;
+/// assert_eq!(lines, vec!["foo", "bar"]);
+/// # Ok(()) } fn main() { try_main().unwrap() }
+/// ```
+pub struct FileReadBuf(io::BufReader<Raw>);
+
+impl FileRead {
+    /// Wrap the file in a buffer, like `std::io::BufReader`.
+    pub fn buffered(self) -> FileReadBuf {
+        FileReadBuf(io::BufReader::new(Raw(self.0)))
+    }
+
+    /// Wrap the file in a buffer with the specified capacity.
+    pub fn buffered_with_capacity(self, capacity: usize) -> FileReadBuf {
+        FileReadBuf(io::BufReader::with_capacity(capacity, Raw(self.0)))
+    }
+}
+
+impl FileReadBuf {
+    /// Read what remains of the file to a `String`.
+    pub fn read_string(&mut self) -> Result<String> {
+        let mut s = String::new();
+        self.0
+            .read_to_string(&mut s)
+            .map_err(|err| Error::new(err, "reading", self.path.clone().into()))?;
+        Ok(s)
+    }
+
+    /// Unwrap the underlying `FileRead`.
+    ///
+    /// > Any data remaining in the buffer is lost.
+    pub fn into_inner(self) -> FileRead {
+        FileRead((self.0.into_inner()).0)
+    }
+}
+
+impl fmt::Debug for FileReadBuf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FileReadBuf(")?;
+        self.path.fmt(f)?;
+        write!(f, ")")
+    }
+}
+
+impl io::Read for FileReadBuf {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0
+            .read(buf)
+            .map_err(|err| Error::new(err, "reading", self.path.clone().into()).into())
+    }
+}
+
+impl io::BufRead for FileReadBuf {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        // The buffer borrows `self`, so the path cannot be borrowed in the closure.
+        let path = self.path().clone();
+        self.0
+            .fill_buf()
+            .map_err(|err| Error::new(err, "reading", path.into()).into())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.0.consume(amt)
+    }
+}
+
+impl io::Seek for FileReadBuf {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        self.0
+            .seek(pos)
+            .map_err(|err| Error::new(err, "seeking", self.path.clone().into()).into())
+    }
+}
+
+impl Deref for FileReadBuf {
+    type Target = FileOpen;
+
+    fn deref(&self) -> &FileOpen {
+        &self.0.get_ref().0
+    }
+}
+
+/// A buffered write-only file handle with `path()` attached and improved error messages,
+/// returned by [`FileWrite::buffered`](struct.FileWrite.html#method.buffered).
+///
+/// This is the equivalent of `std::io::BufWriter<FileWrite>`, except that the path is still
+/// accessible and all errors include it.
+///
+/// > The buffer is flushed when this is dropped. Since the error can't be returned from `drop`
+/// > it is passed to the hook set with [`on_drop_error`](#method.on_drop_error), and ignored if
+/// > there is none. Call [`finish`](#method.finish) (or `flush`) to handle it instead.
+///
+/// # Examples
+/// ```rust
+/// # extern crate path_abs;
+/// # extern crate tempdir;
+/// use std::io::Write;
+/// use path_abs::{FileWrite, PathFile};
+///
+/// # fn try_main() -> ::std::io::Result<()> {
+/// let example = "example.txt";
+/// # let tmp = tempdir::TempDir::new("ex")?;
+/// # let example = &tmp.path().join(example);
+/// let mut write = FileWrite::create(example)?.buffered();
+/// for i in 0..3 {
+///     writeln!(write, "line {}", i)?;
+/// }
+/// write.finish()?;
+///
+/// let file = PathFile::new(example)?;
+/// assert_eq!("line 0\nline 1\nline 2\n", file.read_string()?);
+/// # Ok(()) } fn main() { try_main().unwrap() }
+/// ```
+pub struct FileWriteBuf {
+    // Only `None` once `finish` took it.
+    buf: Option<io::BufWriter<Raw>>,
+    on_drop_error: Option<Box<dyn FnMut(Error) + Send + Sync>>,
+}
+
+impl FileWrite {
+    /// Wrap the file in a buffer, like `std::io::BufWriter`.
+    pub fn buffered(self) -> FileWriteBuf {
+        FileWriteBuf::new(io::BufWriter::new(Raw(self.0)))
+    }
+
+    /// Wrap the file in a buffer with the specified capacity.
+    pub fn buffered_with_capacity(self, capacity: usize) -> FileWriteBuf {
+        FileWriteBuf::new(io::BufWriter::with_capacity(capacity, Raw(self.0)))
+    }
+}
+
+impl FileWriteBuf {
+    fn new(buf: io::BufWriter<Raw>) -> FileWriteBuf {
+        FileWriteBuf {
+            buf: Some(buf),
+            on_drop_error: None,
+        }
+    }
+
+    fn buf(&mut self) -> &mut io::BufWriter<Raw> {
+        self.buf.as_mut().expect("FileWriteBuf used after finish")
+    }
+
+    /// Set the hook called with the error if flushing fails when this is dropped, i.e. to log
+    /// it. It is not called when [`finish`](#method.finish) is used.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::FileWrite;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join("example.txt");
+    /// let mut write = FileWrite::create(example)?.buffered();
+    /// write.on_drop_error(|err| eprintln!("warning: {}", err));
+    /// write.write_str("flushed when dropped")?;
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn on_drop_error<F>(&mut self, hook: F) -> &mut FileWriteBuf
+    where
+        F: FnMut(Error) + Send + Sync + 'static,
+    {
+        self.on_drop_error = Some(Box::new(hook));
+        self
+    }
+
+    /// Shortcut to `self.write_all(s.as_bytes())` with slightly
+    /// improved error message.
+    pub fn write_str(&mut self, s: &str) -> Result<()> {
+        let path = self.path().clone();
+        self.buf()
+            .write_all(s.as_bytes())
+            .map_err(|err| Error::new(err, "writing", path.into()))
+    }
+
+    /// `std::io::BufWriter::flush` but with the new error type.
+    pub fn flush(&mut self) -> Result<()> {
+        let path = self.path().clone();
+        self.buf()
+            .flush()
+            .map_err(|err| Error::new(err, "flushing", path.into()))
+    }
+
+    /// Flush the buffer and return the underlying `FileWrite`.
+    ///
+    /// Unlike dropping the buffer, this returns any error which happens while flushing.
+    pub fn finish(mut self) -> Result<FileWrite> {
+        self.flush()?;
+        let path = self.path().clone();
+        match self.buf.take().map(io::BufWriter::into_inner) {
+            Some(Ok(raw)) => Ok(FileWrite(raw.0)),
+            Some(Err(err)) => Err(Error::new(err.into_error(), "flushing", path.into())),
+            None => unreachable!("FileWriteBuf used after finish"),
+        }
+    }
+}
+
+impl Drop for FileWriteBuf {
+    fn drop(&mut self) {
+        if self.buf.is_none() {
+            return;
+        }
+        // Flush here rather than in the drop of `BufWriter`, which ignores the error.
+        if let Err(err) = self.flush() {
+            // Discard the buffer, so that `BufWriter` doesn't write it again.
+            let _ = self.buf.take().map(io::BufWriter::into_parts);
+            if let Some(ref mut hook) = self.on_drop_error {
+                hook(err);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for FileWriteBuf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FileWriteBuf(")?;
+        self.path.fmt(f)?;
+        write!(f, ")")
+    }
+}
+
+impl io::Write for FileWriteBuf {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let path = self.path().clone();
+        self.buf()
+            .write(buf)
+            .map_err(|err| Error::new(err, "writing to", path.into()).into())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        FileWriteBuf::flush(self).map_err(io::Error::from)
+    }
+}
+
+impl io::Seek for FileWriteBuf {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let path = self.path().clone();
+        self.buf()
+            .seek(pos)
+            .map_err(|err| Error::new(err, "seeking", path.into()).into())
+    }
+}
+
+impl Deref for FileWriteBuf {
+    type Target = FileOpen;
+
+    fn deref(&self) -> &FileOpen {
+        let buf = self.buf.as_ref().expect("FileWriteBuf used after finish");
+        &buf.get_ref().0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::super::{PathAbs, PathArc, PathFile};
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn write_buf_finish_error() {
+        // /dev/full is not a file, so skip PathFile::new
+        let full = PathFile(PathAbs(PathArc::new("/dev/full")));
+        let mut write = FileWrite::open_path(full, fs::OpenOptions::new()).unwrap().buffered();
+        write.write_str("lost").unwrap();
+        let err = write.finish().unwrap_err();
+        assert_eq!("flushing", err.action());
+        assert_eq!(Path::new("/dev/full"), err.path());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn write_buf_drop_error() {
+        use std::sync::{Arc, Mutex};
+
+        let full = PathFile(PathAbs(PathArc::new("/dev/full")));
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        {
+            let mut write = FileWrite::open_path(full, fs::OpenOptions::new()).unwrap().buffered();
+            let errors = errors.clone();
+            write.on_drop_error(move |err| errors.lock().unwrap().push(err.to_string()));
+            write.write_str("lost").unwrap();
+        }
+        let errors = errors.lock().unwrap();
+        assert_eq!(1, errors.len());
+        assert!(errors[0].ends_with("when flushing /dev/full"));
+    }
+}
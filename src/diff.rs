@@ -0,0 +1,491 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Comparing the contents of two directory trees.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::time::SystemTime;
+use std_prelude::*;
+
+use super::{Error, Result};
+use super::{PathArc, PathDir, PathFile, PathType};
+use list::entry_type;
+use utils::{read_dir, BUF_SIZE};
+
+/// How [`PathDir::diff`](struct.PathDir.html#method.diff) decides whether two files differ.
+#[derive(Clone, Copy)]
+pub enum DiffMode {
+    /// The files differ if their size or their modification time differ. This is the cheapest
+    /// mode since it only requires a `stat` of each file.
+    MtimeSize,
+    /// The files differ if their contents differ. Both files are read in full unless their
+    /// sizes differ.
+    Content,
+    /// The files differ if the given hash function returns different values for them. For
+    /// instance `DiffMode::Hash(PathFile::hash::<Sha256>)`.
+    Hash(fn(&PathFile) -> Result<Vec<u8>>),
+}
+
+// Deriving this fails on older compilers because of the lifetime in the `Hash` function.
+impl fmt::Debug for DiffMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DiffMode::MtimeSize => f.write_str("MtimeSize"),
+            DiffMode::Content => f.write_str("Content"),
+            DiffMode::Hash(hash) => f.debug_tuple("Hash").field(&(hash as *const ())).finish(),
+        }
+    }
+}
+
+/// A single difference between two directory trees, returned by
+/// [`PathDir::diff`](struct.PathDir.html#method.diff).
+///
+/// The `path` of every entry is relative to the directories being compared.
+#[derive(Debug, Clone, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub enum DiffEntry {
+    /// The path only exists in the `other` directory.
+    Added { path: PathBuf, new: PathType },
+    /// The path only exists in `self`.
+    Removed { path: PathBuf, old: PathType },
+    /// The path is a file in both directories but the files differ.
+    Modified {
+        path: PathBuf,
+        old: PathFile,
+        new: PathFile,
+    },
+    /// The path is a different type in each directory, i.e. a file in one and a directory in
+    /// the other.
+    ///
+    /// Since symlinks are never descended into, this is also returned when the path is a
+    /// symlink to a directory in one and a real directory in the other. Both sides are then a
+    /// `PathType::Dir`.
+    TypeChanged {
+        path: PathBuf,
+        old: PathType,
+        new: PathType,
+    },
+    /// The path is a symlink to a directory in both directories but the links point to
+    /// different targets.
+    LinkChanged {
+        path: PathBuf,
+        old: PathType,
+        new: PathType,
+    },
+    /// The path is a symlink which can't be resolved in at least one of the directories, and
+    /// it is not the same link in both.
+    ///
+    /// `old` and `new` are the targets of the link in each directory, or `None` if the path
+    /// doesn't exist or isn't a broken symlink there.
+    BrokenLink {
+        path: PathBuf,
+        old: Option<PathBuf>,
+        new: Option<PathBuf>,
+    },
+}
+
+impl DiffEntry {
+    /// The path of the entry, relative to the directories being compared.
+    pub fn path(&self) -> &Path {
+        match *self {
+            DiffEntry::Added { ref path, .. }
+            | DiffEntry::Removed { ref path, .. }
+            | DiffEntry::Modified { ref path, .. }
+            | DiffEntry::TypeChanged { ref path, .. }
+            | DiffEntry::LinkChanged { ref path, .. }
+            | DiffEntry::BrokenLink { ref path, .. } => path,
+        }
+    }
+}
+
+impl PathDir {
+    /// Compare the tree rooted at `self` with the tree rooted at `other`.
+    ///
+    /// Differences are reported going from `self` to `other`: an `Added` entry only exists in
+    /// `other` and a `Removed` entry only exists in `self`. The contents of added and removed
+    /// directories are not listed individually. The entries are sorted by path.
+    ///
+    /// Symlinks are resolved when determining the type of an entry but symlinks to directories
+    /// are never descended into, they are compared by their target instead. Symlinks which
+    /// can't be resolved are also compared by their target, and are reported as
+    /// `DiffEntry::BrokenLink`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::path::PathBuf;
+    /// use path_abs::{DiffEntry, DiffMode, PathDir, PathFile, PathType};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let tmp = PathDir::new(tmp.path())?;
+    /// let staged = PathDir::create(tmp.join("staged"))?;
+    /// let live = PathDir::create(tmp.join("live"))?;
+    ///
+    /// PathFile::create(staged.join("same.txt"))?.write_str("same")?;
+    /// PathFile::create(live.join("same.txt"))?.write_str("same")?;
+    /// let old = PathFile::create(staged.join("changed.txt"))?;
+    /// old.write_str("old")?;
+    /// let new = PathFile::create(live.join("changed.txt"))?;
+    /// new.write_str("new")?;
+    /// let added = PathDir::create(live.join("added"))?;
+    ///
+    /// let diff = staged.diff(&live, DiffMode::Content)?;
+    /// assert_eq!(diff, vec![
+    ///     DiffEntry::Added { path: PathBuf::from("added"), new: PathType::Dir(added) },
+    ///     DiffEntry::Modified { path: PathBuf::from("changed.txt"), old: old, new: new },
+    /// ]);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn diff(&self, other: &PathDir, mode: DiffMode) -> Result<Vec<DiffEntry>> {
+        let mut out = Vec::new();
+        diff_dirs(self, other, Path::new(""), mode, &mut out)
+            .map_err(|err| err.context_dest("comparing", self, other))?;
+        out.sort_by(|a, b| a.path().cmp(b.path()));
+        Ok(out)
+    }
+}
+
+fn diff_dirs(
+    old_dir: &PathDir,
+    new_dir: &PathDir,
+    rel: &Path,
+    mode: DiffMode,
+    out: &mut Vec<DiffEntry>,
+) -> Result<()> {
+    let mut old_entries = list_names(old_dir)?;
+    let new_entries = list_names(new_dir)?;
+
+    for (name, new_entry) in new_entries {
+        let path = rel.join(&name);
+        let old_entry = match old_entries.remove(&name) {
+            Some(e) => e,
+            None => {
+                out.push(match new_entry {
+                    Entry::Resolved(new, _) => DiffEntry::Added { path, new },
+                    Entry::Broken(target) => DiffEntry::BrokenLink {
+                        path,
+                        old: None,
+                        new: Some(target),
+                    },
+                });
+                continue;
+            }
+        };
+        match (old_entry, new_entry) {
+            (Entry::Resolved(PathType::File(old), _), Entry::Resolved(PathType::File(new), _)) => {
+                if files_differ(&old, &new, mode)? {
+                    out.push(DiffEntry::Modified { path, old, new });
+                }
+            }
+            (
+                Entry::Resolved(PathType::Dir(old), old_link),
+                Entry::Resolved(PathType::Dir(new), new_link),
+            ) => {
+                if !old_link && !new_link {
+                    diff_dirs(&old, &new, &path, mode, out)?;
+                } else if old_link != new_link {
+                    out.push(DiffEntry::TypeChanged {
+                        path,
+                        old: PathType::Dir(old),
+                        new: PathType::Dir(new),
+                    });
+                } else if link_target(&old_dir.join(&name))? != link_target(&new_dir.join(&name))? {
+                    out.push(DiffEntry::LinkChanged {
+                        path,
+                        old: PathType::Dir(old),
+                        new: PathType::Dir(new),
+                    });
+                }
+            }
+            (
+                Entry::Resolved(PathType::Other(old), _),
+                Entry::Resolved(PathType::Other(new), _),
+            ) => {
+                // Special files of the same kind have no contents to compare.
+                if old.kind() != new.kind() {
+                    out.push(DiffEntry::TypeChanged {
+                        path,
+                        old: PathType::Other(old),
+                        new: PathType::Other(new),
+                    });
+                }
+            }
+            (Entry::Resolved(old, _), Entry::Resolved(new, _)) => {
+                out.push(DiffEntry::TypeChanged { path, old, new })
+            }
+            (old, new) => {
+                let (old, new) = (old.broken_target(), new.broken_target());
+                if old != new {
+                    out.push(DiffEntry::BrokenLink { path, old, new });
+                }
+            }
+        }
+    }
+
+    for (name, old_entry) in old_entries {
+        let path = rel.join(name);
+        out.push(match old_entry {
+            Entry::Resolved(old, _) => DiffEntry::Removed { path, old },
+            Entry::Broken(target) => DiffEntry::BrokenLink {
+                path,
+                old: Some(target),
+                new: None,
+            },
+        });
+    }
+    Ok(())
+}
+
+/// An entry of a directory being compared.
+enum Entry {
+    /// The type of the entry (with symlinks resolved) and whether it is a symlink.
+    Resolved(PathType, bool),
+    /// A symlink which can't be resolved, with its target.
+    Broken(PathBuf),
+}
+
+impl Entry {
+    fn broken_target(self) -> Option<PathBuf> {
+        match self {
+            Entry::Resolved(..) => None,
+            Entry::Broken(target) => Some(target),
+        }
+    }
+}
+
+/// List the entries of `dir` by name.
+fn list_names(dir: &PathDir) -> Result<BTreeMap<OsString, Entry>> {
+    let mut out = BTreeMap::new();
+    for entry in read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let is_link = entry
+            .file_type()
+            .map_err(|err| Error::new(err, "getting file type of", PathArc::new(&path)))?
+            .is_symlink();
+        let listed = if is_link && fs::metadata(&path).is_err() {
+            Entry::Broken(link_target(&path)?)
+        } else {
+            Entry::Resolved(entry_type(dir, &entry, true)?, is_link)
+        };
+        out.insert(entry.file_name(), listed);
+    }
+    Ok(out)
+}
+
+fn link_target(link: &Path) -> Result<PathBuf> {
+    fs::read_link(link).map_err(|err| Error::new(err, "reading link", PathArc::new(link)))
+}
+
+fn files_differ(old: &PathFile, new: &PathFile, mode: DiffMode) -> Result<bool> {
+    let old_meta = old.metadata()?;
+    let new_meta = new.metadata()?;
+    if old_meta.len() != new_meta.len() {
+        return Ok(true);
+    }
+    match mode {
+        DiffMode::MtimeSize => Ok(modified(old, &old_meta)? != modified(new, &new_meta)?),
+        DiffMode::Content => contents_differ(old, new),
+        DiffMode::Hash(hash) => Ok(hash(old)? != hash(new)?),
+    }
+}
+
+fn modified(path: &PathFile, meta: &fs::Metadata) -> Result<SystemTime> {
+    meta.modified()
+        .map_err(|err| Error::new(err, "getting modified time of", path.clone().into()))
+}
+
+fn contents_differ(old: &PathFile, new: &PathFile) -> Result<bool> {
+    let mut old_read = old.read()?;
+    let mut new_read = new.read()?;
+    let mut old_buf = vec![0; BUF_SIZE];
+    let mut new_buf = vec![0; BUF_SIZE];
+    loop {
+        let n = fill(&mut old_read.0.file, &mut old_buf)
+            .map_err(|err| Error::new(err, "reading", old.clone().into()))?;
+        let m = fill(&mut new_read.0.file, &mut new_buf)
+            .map_err(|err| Error::new(err, "reading", new.clone().into()))?;
+        if old_buf[..n] != new_buf[..m] {
+            return Ok(true);
+        }
+        if n == 0 {
+            return Ok(false);
+        }
+    }
+}
+
+/// Read into `buf` until it is full or the end of the file is reached.
+fn fill(file: &mut fs::File, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match file.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use tempdir::TempDir;
+
+    use super::super::{PathDir, PathFile, PathType};
+    use super::*;
+
+    #[test]
+    fn sanity_diff() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let old = PathDir::create(tmp_abs.join("old")).unwrap();
+        let new = PathDir::create(tmp_abs.join("new")).unwrap();
+
+        // nested files are compared
+        let old_sub = PathDir::create(old.join("sub")).unwrap();
+        let new_sub = PathDir::create(new.join("sub")).unwrap();
+        let old_foo = PathFile::create(old_sub.join("foo.txt")).unwrap();
+        let new_foo = PathFile::create(new_sub.join("foo.txt")).unwrap();
+        old_foo.write_str("foo").unwrap();
+        new_foo.write_str("FOO").unwrap();
+
+        // a file that became a directory
+        let old_ty = PathFile::create(old.join("ty")).unwrap();
+        let new_ty = PathDir::create(new.join("ty")).unwrap();
+
+        // a removed file
+        let removed = PathFile::create(old.join("removed.txt")).unwrap();
+
+        let expected = vec![
+            DiffEntry::Removed {
+                path: PathBuf::from("removed.txt"),
+                old: PathType::File(removed),
+            },
+            DiffEntry::Modified {
+                path: PathBuf::from("sub").join("foo.txt"),
+                old: old_foo.clone(),
+                new: new_foo.clone(),
+            },
+            DiffEntry::TypeChanged {
+                path: PathBuf::from("ty"),
+                old: PathType::File(old_ty),
+                new: PathType::Dir(new_ty),
+            },
+        ];
+        assert_eq!(expected, old.diff(&new, DiffMode::Content).unwrap());
+
+        new_foo.write_str("foo").unwrap();
+        let without_foo = vec![expected[0].clone(), expected[2].clone()];
+        assert_eq!(without_foo, old.diff(&new, DiffMode::Content).unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn diff_broken_symlink() {
+        use std::os::unix::fs::symlink;
+
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let old = PathDir::create(tmp_abs.join("old")).unwrap();
+        let new = PathDir::create(tmp_abs.join("new")).unwrap();
+        symlink("missing", old.join("both")).unwrap();
+        symlink("missing", new.join("both")).unwrap();
+        symlink("missing", new.join("added")).unwrap();
+        symlink("missing", old.join("retargeted")).unwrap();
+        symlink("gone", new.join("retargeted")).unwrap();
+        symlink("missing", old.join("fixed")).unwrap();
+        PathFile::create(new.join("fixed")).unwrap();
+
+        let expected = vec![
+            DiffEntry::BrokenLink {
+                path: PathBuf::from("added"),
+                old: None,
+                new: Some(PathBuf::from("missing")),
+            },
+            DiffEntry::BrokenLink {
+                path: PathBuf::from("fixed"),
+                old: Some(PathBuf::from("missing")),
+                new: None,
+            },
+            DiffEntry::BrokenLink {
+                path: PathBuf::from("retargeted"),
+                old: Some(PathBuf::from("missing")),
+                new: Some(PathBuf::from("gone")),
+            },
+        ];
+        assert_eq!(expected, old.diff(&new, DiffMode::Content).unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn diff_dir_symlink() {
+        use std::os::unix::fs::symlink;
+
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let old = PathDir::create(tmp_abs.join("old")).unwrap();
+        let new = PathDir::create(tmp_abs.join("new")).unwrap();
+        for dir in &[&old, &new] {
+            PathDir::create(dir.join("sub")).unwrap();
+            PathDir::create(dir.join("other")).unwrap();
+            symlink("sub", dir.join("link")).unwrap();
+        }
+        assert_eq!(Vec::<DiffEntry>::new(), old.diff(&new, DiffMode::Content).unwrap());
+
+        // pointing somewhere else is not a type change
+        fs::remove_file(new.join("link")).unwrap();
+        symlink("other", new.join("link")).unwrap();
+        let expected = vec![DiffEntry::LinkChanged {
+            path: PathBuf::from("link"),
+            old: PathType::Dir(PathDir::new(old.join("sub")).unwrap()),
+            new: PathType::Dir(PathDir::new(new.join("other")).unwrap()),
+        }];
+        assert_eq!(expected, old.diff(&new, DiffMode::Content).unwrap());
+
+        // but replacing the link with a real directory is
+        fs::remove_file(new.join("link")).unwrap();
+        let real = PathDir::create(new.join("link")).unwrap();
+        let expected = vec![DiffEntry::TypeChanged {
+            path: PathBuf::from("link"),
+            old: PathType::Dir(PathDir::new(old.join("sub")).unwrap()),
+            new: PathType::Dir(real),
+        }];
+        assert_eq!(expected, old.diff(&new, DiffMode::Content).unwrap());
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    fn diff_hash() {
+        use sha2::Sha256;
+
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let old = PathDir::create(tmp_abs.join("old")).unwrap();
+        let new = PathDir::create(tmp_abs.join("new")).unwrap();
+        let old_foo = PathFile::create(old.join("foo.txt")).unwrap();
+        let new_foo = PathFile::create(new.join("foo.txt")).unwrap();
+        old_foo.write_str("foo").unwrap();
+        new_foo.write_str("foo").unwrap();
+
+        let mode = DiffMode::Hash(PathFile::hash::<Sha256>);
+        assert_eq!(Vec::<DiffEntry>::new(), old.diff(&new, mode).unwrap());
+
+        new_foo.write_str("bar").unwrap();
+        let expected = vec![DiffEntry::Modified {
+            path: PathBuf::from("foo.txt"),
+            old: old_foo,
+            new: new_foo,
+        }];
+        assert_eq!(expected, old.diff(&new, mode).unwrap());
+    }
+}
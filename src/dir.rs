@@ -9,10 +9,13 @@
 use std::fs;
 use std::fmt;
 use std::io;
+use std::convert::TryFrom;
+use std::str::FromStr;
 use std_prelude::*;
 
 use super::{Error, Result};
-use super::{PathAbs, PathArc, PathType};
+use super::{ListDir, ListOptions, PathAbs, PathArc, PathType};
+use other::not_a;
 
 #[derive(Clone, Eq, Hash, PartialEq, PartialOrd, Ord)]
 /// A `PathAbs` that is guaranteed to be a directory, with associated methods.
@@ -81,7 +84,7 @@ impl PathDir {
             Ok(PathDir::from_abs_unchecked(abs))
         } else {
             Err(Error::new(
-                io::Error::new(io::ErrorKind::InvalidInput, "path is not a dir"),
+                io::Error::new(io::ErrorKind::InvalidInput, not_a("dir", &abs)),
                 "resolving",
                 abs.into(),
             ))
@@ -127,6 +130,33 @@ impl PathDir {
         PathDir::new(path)
     }
 
+    /// Instantiate a new `PathDir` to a directory, creating the directory. Unlike
+    /// [`create`](#method.create) this fails with `io::ErrorKind::AlreadyExists` if the path
+    /// exists.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::io;
+    /// use path_abs::PathDir;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let dir = PathDir::create_new(example)?;
+    ///
+    /// let err = PathDir::create_new(example).unwrap_err();
+    /// assert_eq!(io::ErrorKind::AlreadyExists, err.io_error().kind());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn create_new<P: AsRef<Path>>(path: P) -> Result<PathDir> {
+        fs::create_dir(&path)
+            .map_err(|err| Error::new(err, "creating", PathArc::new(&path)))?;
+        PathDir::new(path)
+    }
+
     /// Instantiate a new `PathDir` to a directory, recursively recreating it and all of its parent
     /// components if they are missing.
     ///
@@ -177,6 +207,12 @@ impl PathDir {
     /// > This means that if the directory contains a symlink you may get a path from a completely
     /// > _different directory_.
     ///
+    /// Other entries are not canonicalized: they are the directory joined with their name, and
+    /// their type comes from the directory entry (which usually doesn't need any syscall).
+    ///
+    /// Entries are returned in the order of the filesystem. See
+    /// [`list_with`](#method.list_with) to sort or filter them.
+    ///
     /// # Examples
     /// ```rust
     /// # extern crate path_abs;
@@ -205,12 +241,7 @@ impl PathDir {
     /// assert_eq!(expected, result);
     /// # Ok(()) } fn main() { try_main().unwrap() }
     pub fn list(&self) -> Result<ListDir> {
-        let fsread =
-            fs::read_dir(self).map_err(|err| Error::new(err, "reading dir", self.clone().into()))?;
-        Ok(ListDir {
-            dir: self.clone(),
-            fsread: fsread,
-        })
+        self.list_with(&ListOptions::new())
     }
 
     /// Remove (delete) the _empty_ directory from the filesystem, consuming self.
@@ -296,14 +327,9 @@ impl PathDir {
     /// # Ok(()) } fn main() { try_main().unwrap() }
     /// ```
     pub fn symlink<P: AsRef<Path>>(&self, dst: P) -> Result<()> {
-        symlink_dir(&self, &dst)
-            .map_err(|err| {
-            Error::new(
-                err,
-                &format!("linking to {} from", dst.as_ref().display()),
-                self.clone().into(),
-            )
-            })
+        symlink_dir(self, &dst).map_err(|err| {
+            Error::with_dest(err, "symlinking", self.clone().into(), PathArc::new(&dst))
+        })
     }
 
     /// Return a reference to a basic `std::path::Path`
@@ -319,34 +345,6 @@ impl PathDir {
     }
 }
 
-/// An iterator over `PathType` objects, returned by `PathDir::list`.
-pub struct ListDir {
-    // TODO: this should be a reference...?
-    // Or is this a good excuse to use Arc under the hood everywhere?
-    dir: PathDir,
-    fsread: fs::ReadDir,
-}
-
-impl ::std::iter::Iterator for ListDir {
-    type Item = Result<PathType>;
-    fn next(&mut self) -> Option<Result<PathType>> {
-        let entry = match self.fsread.next() {
-            Some(r) => match r {
-                Ok(e) => e,
-                Err(err) => {
-                    return Some(Err(Error::new(
-                        err,
-                        "iterating over",
-                        self.dir.clone().into(),
-                    )))
-                }
-            },
-            None => return None,
-        };
-        Some(PathType::new(entry.path()))
-    }
-}
-
 impl fmt::Debug for PathDir {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         self.0.fmt(f)
@@ -401,25 +399,25 @@ impl Borrow<PathBuf> for PathDir {
     }
 }
 
-impl<'a> Borrow<PathAbs> for &'a PathDir {
+impl Borrow<PathAbs> for &PathDir {
     fn borrow(&self) -> &PathAbs {
         self.as_ref()
     }
 }
 
-impl<'a> Borrow<PathArc> for &'a PathDir {
+impl Borrow<PathArc> for &PathDir {
     fn borrow(&self) -> &PathArc {
         self.as_ref()
     }
 }
 
-impl<'a> Borrow<Path> for &'a PathDir {
+impl Borrow<Path> for &PathDir {
     fn borrow(&self) -> &Path {
         self.as_ref()
     }
 }
 
-impl<'a> Borrow<PathBuf> for &'a PathDir {
+impl Borrow<PathBuf> for &PathDir {
     fn borrow(&self) -> &PathBuf {
         self.as_ref()
     }
@@ -433,7 +431,7 @@ impl Deref for PathDir {
     }
 }
 
-impl Into<PathAbs> for PathDir {
+impl From<PathDir> for PathAbs {
     /// Downgrades the `PathDir` into a `PathAbs`
     ///
     /// # Examples
@@ -447,19 +445,19 @@ impl Into<PathAbs> for PathDir {
     /// let abs: PathAbs = dir.into();
     /// # Ok(()) } fn main() { try_main().unwrap() }
     /// ```
-    fn into(self) -> PathAbs {
-        self.0
+    fn from(path: PathDir) -> PathAbs {
+        path.0
     }
 }
 
-impl Into<PathArc> for PathDir {
+impl From<PathDir> for PathArc {
     /// Downgrades the `PathDir` into a `PathArc`
-    fn into(self) -> PathArc {
-        (self.0).0
+    fn from(path: PathDir) -> PathArc {
+        (path.0).0
     }
 }
 
-impl Into<PathBuf> for PathDir {
+impl From<PathDir> for PathBuf {
     /// Downgrades the `PathDir` into a `PathBuf`. Avoids a clone if this is the only reference.
     ///
     /// # Examples
@@ -473,16 +471,70 @@ impl Into<PathBuf> for PathDir {
     /// let buf: PathBuf = dir.into();
     /// # Ok(()) } fn main() { try_main().unwrap() }
     /// ```
-    fn into(self) -> PathBuf {
-        let arc: PathArc = self.into();
+    fn from(path: PathDir) -> PathBuf {
+        let arc: PathArc = path.into();
         arc.into()
     }
 }
 
+#[cfg(unix)]
+pub(crate) fn symlink_dir<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Result<()> {
+    ::std::os::unix::fs::symlink(src, dst)
+}
+
+#[cfg(windows)]
+pub(crate) fn symlink_dir<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Result<()> {
+    ::std::os::windows::fs::symlink_dir(src, dst)
+}
+
+impl FromStr for PathDir {
+    type Err = Error;
+
+    /// Equivalent to [`PathDir::new`](#method.new).
+    fn from_str(s: &str) -> Result<PathDir> {
+        PathDir::new(s)
+    }
+}
+
+impl<'a> TryFrom<&'a Path> for PathDir {
+    type Error = Error;
+
+    fn try_from(path: &'a Path) -> Result<PathDir> {
+        PathDir::new(path)
+    }
+}
+
+impl TryFrom<PathBuf> for PathDir {
+    type Error = Error;
+
+    fn try_from(path: PathBuf) -> Result<PathDir> {
+        PathDir::new(path)
+    }
+}
+
+impl TryFrom<PathArc> for PathDir {
+    type Error = Error;
+
+    fn try_from(path: PathArc) -> Result<PathDir> {
+        PathDir::new(path)
+    }
+}
+
+impl TryFrom<PathType> for PathDir {
+    type Error = Error;
+
+    /// Equivalent to [`PathType::into_dir`](enum.PathType.html#method.into_dir).
+    fn try_from(ty: PathType) -> Result<PathDir> {
+        ty.into_dir()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use tempdir::TempDir;
     use std::collections::HashSet;
+    use std::convert::TryFrom;
+    use std::io;
     use super::super::{PathAbs, PathDir, PathFile, PathType};
 
     #[test]
@@ -508,14 +560,54 @@ mod tests {
         let _: PathAbs = foo_dir.into();
         let _: PathAbs = bar_file.into();
     }
-}
 
-#[cfg(unix)]
-fn symlink_dir<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Result<()> {
-    ::std::os::unix::fs::symlink(src, dst)
-}
+    #[test]
+    fn list_files_dirs() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let foo_dir = PathDir::create(tmp_abs.join("foo")).unwrap();
+        let bar_file = PathFile::create(tmp_abs.join("bar.txt")).unwrap();
 
-#[cfg(windows)]
-fn symlink_dir<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Result<()> {
-    ::std::os::windows::fs::symlink_dir(src, dst)
+        let files: Vec<_> = tmp_abs.list().unwrap().files().map(|f| f.unwrap()).collect();
+        assert_eq!(vec![bar_file], files);
+        let dirs: Vec<_> = tmp_abs.list().unwrap().dirs().map(|d| d.unwrap()).collect();
+        assert_eq!(vec![foo_dir], dirs);
+    }
+
+    #[test]
+    fn try_from_path_type() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let foo_dir = PathDir::create(tmp_abs.join("foo")).unwrap();
+        let bar_file = PathFile::create(tmp_abs.join("bar.txt")).unwrap();
+
+        assert_eq!(foo_dir, PathDir::try_from(PathType::Dir(foo_dir.clone())).unwrap());
+        assert_eq!(bar_file, PathFile::try_from(PathType::File(bar_file.clone())).unwrap());
+
+        let err = PathDir::try_from(PathType::File(bar_file.clone())).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, err.io_error().kind());
+        assert_eq!(bar_file.as_path(), err.path());
+        assert!(PathFile::try_from(PathType::Dir(foo_dir.clone())).is_err());
+
+        assert_eq!(foo_dir, PathDir::try_from(tmp_abs.join("foo")).unwrap());
+        assert_eq!(PathType::Dir(foo_dir), tmp_abs.join("foo").to_str().unwrap().parse().unwrap());
+    }
+
+    #[test]
+    fn create_new() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+
+        let foo = PathDir::create_new(tmp_abs.join("foo")).unwrap();
+        assert!(foo.is_dir());
+        let err = PathDir::create_new(&foo).unwrap_err();
+        assert_eq!(io::ErrorKind::AlreadyExists, err.io_error().kind());
+        // the OS error is kept
+        assert!(err.io_error().raw_os_error().is_some());
+        let code = err.io_error().raw_os_error().unwrap();
+        assert_eq!(
+            format!("path already exists (os error {}) when creating {}", code, foo.display()),
+            err.to_string()
+        );
+    }
 }
@@ -182,43 +182,35 @@ impl fmt::Debug for FileEdit {
 
 impl io::Read for FileEdit {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.0.file.read(buf).map_err(|err| {
-            io::Error::new(
-                err.kind(),
-                format!("{} when reading {}", err, self.path().display()),
-            )
-        })
+        self.0
+            .file
+            .read(buf)
+            .map_err(|err| Error::new(err, "reading", self.path.clone().into()).into())
     }
 }
 
 impl io::Write for FileEdit {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.0.file.write(buf).map_err(|err| {
-            io::Error::new(
-                err.kind(),
-                format!("{} when writing to {}", err, self.path().display()),
-            )
-        })
+        self.0
+            .file
+            .write(buf)
+            .map_err(|err| Error::new(err, "writing to", self.path.clone().into()).into())
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        self.0.file.flush().map_err(|err| {
-            io::Error::new(
-                err.kind(),
-                format!("{} when flushing {}", err, self.path().display()),
-            )
-        })
+        self.0
+            .file
+            .flush()
+            .map_err(|err| Error::new(err, "flushing", self.path.clone().into()).into())
     }
 }
 
 impl io::Seek for FileEdit {
     fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
-        self.0.file.seek(pos).map_err(|err| {
-            io::Error::new(
-                err.kind(),
-                format!("{} seeking {}", err, self.path().display()),
-            )
-        })
+        self.0
+            .file
+            .seek(pos)
+            .map_err(|err| Error::new(err, "seeking", self.path.clone().into()).into())
     }
 }
 
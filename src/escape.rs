@@ -0,0 +1,180 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Lossless string encoding of paths.
+
+use std::fmt;
+use std::io;
+use stfu8;
+
+#[cfg(unix)]
+use std::ffi::OsStr;
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
+
+#[cfg(windows)]
+use std::ffi::OsString;
+#[cfg(windows)]
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+
+use super::{Error, PathAbs, PathArc, PathDir, PathFile};
+
+impl PathArc {
+    /// Encode the path as a [STFU-8](https://docs.rs/stfu8) string.
+    ///
+    /// Unlike `display()` or `to_string_lossy()` this is lossless: any invalid UTF-8 (or, on
+    /// windows, ill-formed UTF-16) is escaped and can be decoded again with
+    /// [`from_stfu8`](#method.from_stfu8). This is the encoding used for serialization.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// use path_abs::PathArc;
+    ///
+    /// # fn main() {
+    /// let path = PathArc::new("foo/bar\tbaz");
+    /// assert_eq!(r"foo/bar\tbaz", path.to_stfu8());
+    /// assert_eq!(path, PathArc::from_stfu8(&path.to_stfu8()).unwrap());
+    /// # }
+    /// ```
+    #[cfg(unix)]
+    pub fn to_stfu8(&self) -> String {
+        let bytes = self.as_os_str().as_bytes();
+        stfu8::encode_u8(bytes)
+    }
+
+    /// Encode the path as a [STFU-8](https://docs.rs/stfu8) string.
+    ///
+    /// Unlike `display()` or `to_string_lossy()` this is lossless: any invalid UTF-8 (or, on
+    /// windows, ill-formed UTF-16) is escaped and can be decoded again with
+    /// [`from_stfu8`](#method.from_stfu8). This is the encoding used for serialization.
+    #[cfg(windows)]
+    pub fn to_stfu8(&self) -> String {
+        let wide: Vec<u16> = self.as_os_str().encode_wide().collect();
+        stfu8::encode_u16(&wide)
+    }
+
+    /// Decode a path encoded with [`to_stfu8`](#method.to_stfu8).
+    #[cfg(unix)]
+    pub fn from_stfu8(s: &str) -> Result<PathArc, stfu8::DecodeError> {
+        let raw_path = stfu8::decode_u8(s)?;
+        let os_str = OsStr::from_bytes(&raw_path);
+        Ok(PathArc::new(os_str))
+    }
+
+    /// Decode a path encoded with [`to_stfu8`](#method.to_stfu8).
+    #[cfg(windows)]
+    pub fn from_stfu8(s: &str) -> Result<PathArc, stfu8::DecodeError> {
+        let raw_path = stfu8::decode_u16(&s)?;
+        let os_str = OsString::from_wide(&raw_path);
+        Ok(PathArc::new(os_str))
+    }
+
+    /// Return an object implementing `Display` which shows the path in its
+    /// [STFU-8](#method.to_stfu8) encoding.
+    ///
+    /// Use this instead of `display()` in logs: invalid bytes are shown as escapes (i.e. `\xFF`)
+    /// instead of being replaced by `U+FFFD`, so different paths always look different.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// use path_abs::PathArc;
+    ///
+    /// # fn main() {
+    /// let path = PathArc::new("/tmp/foo\nbar");
+    /// assert_eq!(r"/tmp/foo\nbar", path.display_escaped().to_string());
+    /// # }
+    /// ```
+    pub fn display_escaped<'a>(&'a self) -> DisplayEscaped<'a> {
+        DisplayEscaped { path: self }
+    }
+}
+
+/// Helper struct for showing a path with invalid characters escaped, returned by
+/// [`PathArc::display_escaped`](struct.PathArc.html#method.display_escaped).
+pub struct DisplayEscaped<'a> {
+    path: &'a PathArc,
+}
+
+impl<'a> fmt::Debug for DisplayEscaped<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.path.to_stfu8(), f)
+    }
+}
+
+impl<'a> fmt::Display for DisplayEscaped<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.path.to_stfu8())
+    }
+}
+
+/// Decode an STFU-8 path, with an `Error` for the path types' constructors.
+fn decode(s: &str) -> ::Result<PathArc> {
+    PathArc::from_stfu8(s).map_err(|err| {
+        Error::new(
+            io::Error::new(io::ErrorKind::InvalidData, err),
+            "decoding stfu8",
+            PathArc::new(s),
+        )
+    })
+}
+
+impl PathAbs {
+    /// Decode a path encoded with [`PathArc::to_stfu8`](struct.PathArc.html#method.to_stfu8)
+    /// and resolve it with [`PathAbs::new`](#method.new).
+    pub fn from_stfu8(s: &str) -> ::Result<PathAbs> {
+        PathAbs::new(decode(s)?)
+    }
+}
+
+impl PathFile {
+    /// Decode a path encoded with [`PathArc::to_stfu8`](struct.PathArc.html#method.to_stfu8)
+    /// and check that it is a file with [`PathFile::new`](#method.new).
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let lib = PathFile::new("src/lib.rs")?;
+    /// assert_eq!(lib, PathFile::from_stfu8(&lib.to_stfu8())?);
+    /// assert!(PathFile::from_stfu8("src").is_err());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn from_stfu8(s: &str) -> ::Result<PathFile> {
+        PathFile::new(decode(s)?)
+    }
+}
+
+impl PathDir {
+    /// Decode a path encoded with [`PathArc::to_stfu8`](struct.PathArc.html#method.to_stfu8)
+    /// and check that it is a directory with [`PathDir::new`](#method.new).
+    pub fn from_stfu8(s: &str) -> ::Result<PathDir> {
+        PathDir::new(decode(s)?)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::super::{PathArc, PathFile};
+
+    #[test]
+    fn stfu8_invalid_utf8() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = PathArc::new(OsStr::from_bytes(b"/tmp/foo\xFFbar"));
+        assert_eq!("/tmp/foo\u{FFFD}bar", path.display().to_string());
+        assert_eq!(r"/tmp/foo\xFFbar", path.display_escaped().to_string());
+        assert_eq!(path, PathArc::from_stfu8(&path.to_stfu8()).unwrap());
+
+        let err = PathFile::from_stfu8(r"/tmp/foo\xZZ").unwrap_err();
+        assert_eq!("decoding stfu8", err.action());
+    }
+}
@@ -8,10 +8,14 @@
 use std::fs;
 use std::fmt;
 use std::io;
+use std::convert::TryFrom;
+use std::ffi::OsStr;
+use std::str::FromStr;
 use std_prelude::*;
 
 use super::{Error, Result};
-use super::{FileEdit, FileRead, FileWrite, PathAbs, PathArc};
+use super::{FileEdit, FileRead, FileWrite, PathAbs, PathArc, PathType};
+use other::not_a;
 
 #[derive(Clone, Eq, Hash, PartialEq, PartialOrd, Ord)]
 /// a `PathAbs` that was a file at the time of initialization, with associated methods.
@@ -61,7 +65,7 @@ impl PathFile {
             Ok(PathFile::from_abs_unchecked(abs))
         } else {
             Err(Error::new(
-                io::Error::new(io::ErrorKind::InvalidInput, "path is not a file"),
+                io::Error::new(io::ErrorKind::InvalidInput, not_a("file", &abs)),
                 "resolving",
                 abs.into(),
             ))
@@ -103,11 +107,46 @@ impl PathFile {
         fs::OpenOptions::new()
             .write(true)
             .create(true)
+            .truncate(false)
             .open(&path)
             .map_err(|err| Error::new(err, "opening", PathArc::new(&path)))?;
         PathFile::new(path)
     }
 
+    /// Instantiate a new `PathFile`, creating an empty file. Unlike
+    /// [`create`](#method.create) this fails with `io::ErrorKind::AlreadyExists` if the path
+    /// exists.
+    ///
+    /// The file is created atomically (with `O_EXCL`), so only one of several processes trying
+    /// to create it at the same time succeeds.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::io;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create_new(example)?;
+    ///
+    /// let err = PathFile::create_new(example).unwrap_err();
+    /// assert_eq!(io::ErrorKind::AlreadyExists, err.io_error().kind());
+    /// assert_eq!(file.as_path(), err.path());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn create_new<P: AsRef<Path>>(path: P) -> Result<PathFile> {
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .map_err(|err| Error::new(err, "creating", PathArc::new(&path)))?;
+        PathFile::new(path)
+    }
+
     /// Read the entire contents of the file into a `String`.
     ///
     /// # Examples
@@ -306,14 +345,10 @@ impl PathFile {
     /// # Ok(()) } fn main() { try_main().unwrap() }
     /// ```
     pub fn copy<P: AsRef<Path>>(&self, path: P) -> Result<PathFile> {
-        fs::copy(&self, &path).map_err(|err| {
-            Error::new(
-                err,
-                &format!("copying {} from", path.as_ref().display()),
-                self.clone().into(),
-            )
+        fs::copy(self, &path).map_err(|err| {
+            Error::with_dest(err, "copying", self.clone().into(), PathArc::new(&path))
         })?;
-        Ok(PathFile::new(path)?)
+        PathFile::new(path)
     }
 
     /// Rename a file, replacing the original file if `to` already exists.
@@ -344,13 +379,9 @@ impl PathFile {
     /// ```
     pub fn rename<P: AsRef<Path>>(self, to: P) -> Result<PathFile> {
         fs::rename(&self, &to).map_err(|err| {
-            Error::new(
-                err,
-                &format!("renaming to {} from", to.as_ref().display()),
-                self.clone().into(),
-            )
+            Error::with_dest(err, "renaming", self.clone().into(), PathArc::new(&to))
         })?;
-        Ok(PathFile::new(to)?)
+        PathFile::new(to)
     }
 
     /// Creates a new symbolic link on the filesystem to the dst.
@@ -383,14 +414,9 @@ impl PathFile {
     /// # Ok(()) } fn main() { try_main().unwrap() }
     /// ```
     pub fn symlink<P: AsRef<Path>>(&self, dst: P) -> Result<()> {
-        symlink_file(&self, &dst)
-            .map_err(|err| {
-            Error::new(
-                err,
-                &format!("linking to {} from", dst.as_ref().display()),
-                self.clone().into(),
-            )
-            })
+        symlink_file(self, &dst).map_err(|err| {
+            Error::with_dest(err, "symlinking", self.clone().into(), PathArc::new(&dst))
+        })
     }
 
     /// Remove (delete) the file from the filesystem, consuming self.
@@ -420,6 +446,25 @@ impl PathFile {
         fs::remove_file(&self).map_err(|err| Error::new(err, "removing", self.into()))
     }
 
+    /// Get the path of the entry called `name` next to the file, i.e. in the same directory.
+    ///
+    /// > This does not make any syscall, the path may or may not exist.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let lib = PathFile::new("src/lib.rs")?;
+    /// let dir = PathFile::new(lib.sibling("dir.rs"))?;
+    /// assert_eq!(PathFile::new("src/dir.rs")?, dir);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn sibling<P: AsRef<OsStr>>(&self, name: P) -> PathArc {
+        self.with_file_name(name)
+    }
+
     /// Return a reference to a basic `std::path::Path`
     pub fn as_path(&self) -> &Path {
         self.as_ref()
@@ -487,25 +532,25 @@ impl Borrow<PathBuf> for PathFile {
     }
 }
 
-impl<'a> Borrow<PathAbs> for &'a PathFile {
+impl Borrow<PathAbs> for &PathFile {
     fn borrow(&self) -> &PathAbs {
         self.as_ref()
     }
 }
 
-impl<'a> Borrow<PathArc> for &'a PathFile {
+impl Borrow<PathArc> for &PathFile {
     fn borrow(&self) -> &PathArc {
         self.as_ref()
     }
 }
 
-impl<'a> Borrow<Path> for &'a PathFile {
+impl Borrow<Path> for &PathFile {
     fn borrow(&self) -> &Path {
         self.as_ref()
     }
 }
 
-impl<'a> Borrow<PathBuf> for &'a PathFile {
+impl Borrow<PathBuf> for &PathFile {
     fn borrow(&self) -> &PathBuf {
         self.as_ref()
     }
@@ -519,7 +564,7 @@ impl Deref for PathFile {
     }
 }
 
-impl Into<PathAbs> for PathFile {
+impl From<PathFile> for PathAbs {
     /// Downgrades the `PathFile` into a `PathAbs`
     ///
     /// # Examples
@@ -533,19 +578,19 @@ impl Into<PathAbs> for PathFile {
     /// let abs: PathAbs = file.clone().into();
     /// # Ok(()) } fn main() { try_main().unwrap() }
     /// ```
-    fn into(self) -> PathAbs {
-        self.0
+    fn from(path: PathFile) -> PathAbs {
+        path.0
     }
 }
 
-impl Into<PathArc> for PathFile {
+impl From<PathFile> for PathArc {
     /// Downgrades the `PathFile` into a `PathArc`
-    fn into(self) -> PathArc {
-        (self.0).0
+    fn from(path: PathFile) -> PathArc {
+        (path.0).0
     }
 }
 
-impl Into<PathBuf> for PathFile {
+impl From<PathFile> for PathBuf {
     /// Downgrades the `PathFile` into a `PathBuf`. Avoids a clone if this is the only reference.
     ///
     /// # Examples
@@ -559,18 +604,99 @@ impl Into<PathBuf> for PathFile {
     /// let buf: PathBuf = file.into();
     /// # Ok(()) } fn main() { try_main().unwrap() }
     /// ```
-    fn into(self) -> PathBuf {
-        let arc: PathArc = self.into();
+    fn from(path: PathFile) -> PathBuf {
+        let arc: PathArc = path.into();
         arc.into()
     }
 }
 
 #[cfg(unix)]
-fn symlink_file<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Result<()> {
+pub(crate) fn symlink_file<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Result<()> {
     ::std::os::unix::fs::symlink(src, dst)
 }
 
 #[cfg(windows)]
-fn symlink_file<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Result<()> {
+pub(crate) fn symlink_file<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Result<()> {
    :: std::os::windows::fs::symlink_file(src, dst)
 }
+
+impl FromStr for PathFile {
+    type Err = Error;
+
+    /// Equivalent to [`PathFile::new`](#method.new).
+    fn from_str(s: &str) -> Result<PathFile> {
+        PathFile::new(s)
+    }
+}
+
+impl<'a> TryFrom<&'a Path> for PathFile {
+    type Error = Error;
+
+    /// Equivalent to [`PathFile::new`](#method.new). The same conversion exists from `PathBuf`
+    /// and `PathArc`, and `FromStr` is implemented so strings can be `parse`d.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate path_abs;
+    /// use std::convert::TryFrom;
+    /// use std::path::Path;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let lib = PathFile::try_from(Path::new("src/lib.rs"))?;
+    /// assert_eq!(lib, "src/lib.rs".parse()?);
+    /// assert!("src".parse::<PathFile>().is_err());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    fn try_from(path: &'a Path) -> Result<PathFile> {
+        PathFile::new(path)
+    }
+}
+
+impl TryFrom<PathBuf> for PathFile {
+    type Error = Error;
+
+    fn try_from(path: PathBuf) -> Result<PathFile> {
+        PathFile::new(path)
+    }
+}
+
+impl TryFrom<PathArc> for PathFile {
+    type Error = Error;
+
+    fn try_from(path: PathArc) -> Result<PathFile> {
+        PathFile::new(path)
+    }
+}
+
+impl TryFrom<PathType> for PathFile {
+    type Error = Error;
+
+    /// Equivalent to [`PathType::into_file`](enum.PathType.html#method.into_file).
+    fn try_from(ty: PathType) -> Result<PathFile> {
+        ty.into_file()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use tempdir::TempDir;
+
+    use super::super::{PathDir, PathFile};
+
+    #[test]
+    fn create_new() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let foo = tmp_abs.join("foo.txt");
+
+        let file = PathFile::create_new(&foo).unwrap();
+        file.write_str("foo").unwrap();
+        let err = PathFile::create_new(&foo).unwrap_err();
+        assert_eq!(io::ErrorKind::AlreadyExists, err.io_error().kind());
+        assert_eq!(foo.as_path(), err.path());
+        assert!(err.to_string().starts_with("path already exists"));
+        assert_eq!("foo", file.read_string().unwrap());
+    }
+}
@@ -0,0 +1,316 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Content hashing of files and directory trees.
+
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std_prelude::*;
+
+use digest::Digest;
+
+use super::{Error, Result};
+use super::{PathAbs, PathArc, PathDir, PathFile};
+use other::OtherKind;
+use utils::{os_str_bytes, read_dir, BUF_SIZE};
+
+/// The error returned inside of an [`Error`] by [`PathFile::verify`] when the hash of the file
+/// does not match the expected value.
+///
+/// Use [`io::Error::get_ref`] on [`Error::io_error`] to access it.
+///
+/// [`Error`]: struct.Error.html
+/// [`Error::io_error`]: struct.Error.html#method.io_error
+/// [`PathFile::verify`]: struct.PathFile.html#method.verify
+/// [`io::Error::get_ref`]: https://doc.rust-lang.org/std/io/struct.Error.html#method.get_ref
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct HashMismatch {
+    expected: Vec<u8>,
+    actual: Vec<u8>,
+}
+
+impl HashMismatch {
+    /// The hash that was expected.
+    pub fn expected(&self) -> &[u8] {
+        &self.expected
+    }
+
+    /// The hash that was actually computed.
+    pub fn actual(&self) -> &[u8] {
+        &self.actual
+    }
+}
+
+impl fmt::Display for HashMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "hash mismatch: expected {} but got {}",
+            to_hex(&self.expected),
+            to_hex(&self.actual)
+        )
+    }
+}
+
+impl error::Error for HashMismatch {}
+
+impl PathFile {
+    /// Compute the hash of the file's contents using the [`Digest`] algorithm `D`.
+    ///
+    /// The file is streamed through the hasher, so it is never loaded into memory all at once.
+    ///
+    /// [`Digest`]: https://docs.rs/digest/0.10/digest/trait.Digest.html
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate sha2;
+    /// # extern crate tempdir;
+    /// use sha2::Sha256;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    /// file.write_str("foo")?;
+    ///
+    /// let hash = file.hash::<Sha256>()?;
+    /// assert_eq!(hash[..4], [0x2c, 0x26, 0xb4, 0x6b]);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn hash<D: Digest>(&self) -> Result<Vec<u8>> {
+        let mut hasher = D::new();
+        let mut f = self.read()?;
+        let mut buf = vec![0; BUF_SIZE];
+        loop {
+            let n = match f.0.file.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(Error::new(err, "hashing", self.clone().into())),
+            };
+            hasher.update(&buf[..n]);
+        }
+        Ok(hasher.finalize().to_vec())
+    }
+
+    /// Verify that the hash of the file's contents (computed with `D`) is `expected`.
+    ///
+    /// If the hash differs, the returned error has kind `io::ErrorKind::InvalidData` and wraps a
+    /// [`HashMismatch`](struct.HashMismatch.html).
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate sha2;
+    /// # extern crate tempdir;
+    /// use sha2::Sha256;
+    /// use path_abs::{HashMismatch, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    /// file.write_str("foo")?;
+    ///
+    /// let hash = file.hash::<Sha256>()?;
+    /// file.verify::<Sha256>(&hash)?;
+    ///
+    /// file.write_str("bar")?;
+    /// let err = file.verify::<Sha256>(&hash).unwrap_err();
+    /// assert_eq!(err.path(), file.as_path());
+    ///
+    /// let mismatch = err.io_error()
+    ///     .get_ref()
+    ///     .and_then(|e| e.downcast_ref::<HashMismatch>())
+    ///     .unwrap();
+    /// assert_eq!(mismatch.expected(), &hash[..]);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn verify<D: Digest>(&self, expected: &[u8]) -> Result<()> {
+        let actual = self.hash::<D>()?;
+        if actual[..] == expected[..] {
+            return Ok(());
+        }
+        let mismatch = HashMismatch {
+            expected: expected.to_vec(),
+            actual,
+        };
+        Err(Error::new(
+            io::Error::new(io::ErrorKind::InvalidData, mismatch),
+            "verifying hash of",
+            self.clone().into(),
+        ))
+    }
+}
+
+impl PathDir {
+    /// Compute a hash of the entire directory tree using the [`Digest`] algorithm `D`.
+    ///
+    /// The hash is deterministic: entries are visited depth-first, sorted by name, and each one
+    /// contributes its path relative to `self`, its type, its permission bits and (for files)
+    /// the hash of its contents. Symlinks are _not_ followed, their target is hashed instead.
+    /// Special files (i.e. sockets) only contribute their path, type and permissions.
+    ///
+    /// The name of `self` is not part of the hash, so two copies of the same tree in different
+    /// locations have the same hash.
+    ///
+    /// [`Digest`]: https://docs.rs/digest/0.10/digest/trait.Digest.html
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate sha2;
+    /// # extern crate tempdir;
+    /// use sha2::Sha256;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let tmp = PathDir::new(tmp.path())?;
+    /// let a = PathDir::create(tmp.join("a"))?;
+    /// let b = PathDir::create(tmp.join("b"))?;
+    /// PathFile::create(a.join("foo.txt"))?.write_str("foo")?;
+    /// PathFile::create(b.join("foo.txt"))?.write_str("foo")?;
+    ///
+    /// assert_eq!(a.tree_hash::<Sha256>()?, b.tree_hash::<Sha256>()?);
+    ///
+    /// PathFile::create(b.join("bar.txt"))?;
+    /// assert_ne!(a.tree_hash::<Sha256>()?, b.tree_hash::<Sha256>()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn tree_hash<D: Digest>(&self) -> Result<Vec<u8>> {
+        let mut hasher = D::new();
+        hash_tree::<D>(&mut hasher, self.as_path(), &mut Vec::new())
+            .map_err(|err| err.context("hashing tree", self))?;
+        Ok(hasher.finalize().to_vec())
+    }
+}
+
+/// Recursively feed the entries of `dir` into `hasher`, in sorted order.
+///
+/// `rel` is the path of `dir` relative to the root of the tree, as `/` separated bytes.
+fn hash_tree<D: Digest>(hasher: &mut D, dir: &Path, rel: &mut Vec<u8>) -> Result<()> {
+    let mut entries = Vec::new();
+    for entry in read_dir(&PathArc::new(dir))? {
+        let entry = entry?;
+        entries.push((os_str_bytes(&entry.file_name()), entry.path()));
+    }
+    entries.sort();
+
+    for (name, path) in entries {
+        let len = rel.len();
+        if len != 0 {
+            rel.push(b'/');
+        }
+        rel.extend_from_slice(&name);
+
+        let meta = path.symlink_metadata()
+            .map_err(|err| Error::new(err, "getting symlink_metadata of", PathArc::new(&path)))?;
+        let ty = meta.file_type();
+        let other = OtherKind::from_file_type(&ty);
+        let tag = if ty.is_symlink() {
+            b'l'
+        } else if ty.is_dir() {
+            b'd'
+        } else if other.is_some() {
+            b'o'
+        } else {
+            b'f'
+        };
+
+        hasher.update([tag]);
+        hasher.update((rel.len() as u64).to_le_bytes());
+        hasher.update(&rel);
+        hasher.update(mode(&meta).to_le_bytes());
+
+        if ty.is_symlink() {
+            let target = fs::read_link(&path)
+                .map_err(|err| Error::new(err, "reading link", PathArc::new(&path)))?;
+            let target = os_str_bytes(target.as_os_str());
+            hasher.update((target.len() as u64).to_le_bytes());
+            hasher.update(&target);
+        } else if ty.is_dir() {
+            hash_tree::<D>(hasher, &path, rel)?;
+        } else if other.is_none() {
+            let file = PathFile(PathAbs(PathArc::from(path)));
+            hasher.update(file.hash::<D>()?);
+        }
+
+        rel.truncate(len);
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn mode(meta: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    meta.permissions().mode()
+}
+
+#[cfg(windows)]
+fn mode(meta: &fs::Metadata) -> u32 {
+    meta.permissions().readonly() as u32
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use sha2::Sha256;
+    use tempdir::TempDir;
+
+    use super::super::{PathDir, PathFile};
+
+    #[test]
+    fn sanity_tree_hash() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+
+        let a = PathDir::create(tmp_abs.join("a")).unwrap();
+        PathDir::create(a.join("sub")).unwrap();
+        PathFile::create(a.join("sub").join("foo.txt")).unwrap().write_str("foo").unwrap();
+        PathFile::create(a.join("bar.txt")).unwrap().write_str("bar").unwrap();
+
+        let hash = a.tree_hash::<Sha256>().unwrap();
+        assert_eq!(hash, a.tree_hash::<Sha256>().unwrap());
+
+        // changing contents changes the hash
+        let foo = PathFile::new(a.join("sub").join("foo.txt")).unwrap();
+        foo.write_str("FOO").unwrap();
+        let changed = a.tree_hash::<Sha256>().unwrap();
+        assert_ne!(hash, changed);
+
+        // moving a file with the same contents changes the hash
+        foo.write_str("foo").unwrap();
+        assert_eq!(hash, a.tree_hash::<Sha256>().unwrap());
+        foo.rename(a.join("foo.txt")).unwrap();
+        assert_ne!(hash, a.tree_hash::<Sha256>().unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn tree_hash_mode() {
+        use std::fs;
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let foo = PathFile::create(tmp_abs.join("foo.sh")).unwrap();
+
+        let hash = tmp_abs.tree_hash::<Sha256>().unwrap();
+        fs::set_permissions(&foo, fs::Permissions::from_mode(0o755)).unwrap();
+        assert_ne!(hash, tmp_abs.tree_hash::<Sha256>().unwrap());
+    }
+}
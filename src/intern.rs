@@ -0,0 +1,187 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Deduplicating the allocations of equal paths.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Mutex;
+use std_prelude::*;
+
+use super::{PathAbs, PathArc, PathDir, PathFile};
+
+/// A set of paths which makes equal paths share a single allocation.
+///
+/// Use a `PathInterner` to scope the interned paths (they are freed when it is dropped) or the
+/// [`global`](#method.global) one, which lives for the whole program.
+///
+/// Interning is optional: `PathArc::new` and the constructors of the other path types never
+/// intern, and paths which are not interned work exactly the same, they just don't benefit from
+/// it. Use [`intern_abs`](#method.intern_abs), [`intern_file`](#method.intern_file) and
+/// [`intern_dir`](#method.intern_dir) to intern the typed paths.
+///
+/// # Examples
+/// ```rust
+/// # extern crate path_abs;
+/// use path_abs::{PathArc, PathInterner};
+///
+/// let interner = PathInterner::new();
+/// let a = interner.intern("src/lib.rs");
+/// let b = interner.intern(PathArc::new("src").join("lib.rs"));
+///
+/// assert!(a.ptr_eq(&b));
+/// assert_eq!(1, interner.len());
+/// ```
+#[derive(Default)]
+pub struct PathInterner {
+    // `None` until the first path is interned, so that the global interner can be a `static`.
+    paths: Mutex<Option<HashSet<PathArc>>>,
+}
+
+impl PathInterner {
+    /// Create an empty interner.
+    pub fn new() -> PathInterner {
+        PathInterner::default()
+    }
+
+    /// The interner shared by the whole program. Its paths are never freed unless
+    /// [`purge`](#method.purge) is called.
+    pub fn global() -> &'static PathInterner {
+        static GLOBAL: PathInterner = PathInterner {
+            paths: Mutex::new(None),
+        };
+        &GLOBAL
+    }
+
+    /// Get the interned `PathArc` which is equal to `path`, interning it if there is none.
+    pub fn intern<P: AsRef<Path>>(&self, path: P) -> PathArc {
+        let path = path.as_ref();
+        self.with_paths(|paths| {
+            if let Some(interned) = paths.get(path) {
+                return interned.clone();
+            }
+            let interned = PathArc::new(path);
+            paths.insert(interned.clone());
+            interned
+        })
+    }
+
+    /// Get the interned `PathAbs` which is equal to `abs`, interning it if there is none.
+    ///
+    /// If there is none `abs` itself is interned, so this never allocates a new path.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// use path_abs::{PathDir, PathInterner};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let interner = PathInterner::new();
+    /// let a = interner.intern_dir(PathDir::current_dir()?);
+    /// let b = interner.intern_dir(PathDir::new(".")?);
+    ///
+    /// assert_eq!(a, b);
+    /// assert_eq!(1, interner.len());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn intern_abs(&self, abs: PathAbs) -> PathAbs {
+        PathAbs(self.intern_arc(abs.0))
+    }
+
+    /// Get the interned `PathFile` which is equal to `file`, interning it if there is none.
+    ///
+    /// See [`intern_abs`](#method.intern_abs).
+    pub fn intern_file(&self, file: PathFile) -> PathFile {
+        PathFile(self.intern_abs(file.0))
+    }
+
+    /// Get the interned `PathDir` which is equal to `dir`, interning it if there is none.
+    ///
+    /// See [`intern_abs`](#method.intern_abs).
+    pub fn intern_dir(&self, dir: PathDir) -> PathDir {
+        PathDir(self.intern_abs(dir.0))
+    }
+
+    /// Like `intern` but reuses the allocation of `path` if it is not interned yet.
+    fn intern_arc(&self, path: PathArc) -> PathArc {
+        self.with_paths(|paths| {
+            if let Some(interned) = paths.get(&path) {
+                return interned.clone();
+            }
+            paths.insert(path.clone());
+            path
+        })
+    }
+
+    fn with_paths<T, F: FnOnce(&mut HashSet<PathArc>) -> T>(&self, f: F) -> T {
+        let mut paths = self.paths.lock().expect("poisoned");
+        f(paths.get_or_insert_with(HashSet::new))
+    }
+
+    /// The number of interned paths.
+    pub fn len(&self) -> usize {
+        self.with_paths(|paths| paths.len())
+    }
+
+    /// Returns true if no paths are interned.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Free the interned paths which are not used anywhere else. Returns how many were freed.
+    pub fn purge(&self) -> usize {
+        self.with_paths(|paths| {
+            let before = paths.len();
+            paths.retain(|p| Arc::strong_count(&p.0) > 1);
+            before - paths.len()
+        })
+    }
+}
+
+impl fmt::Debug for PathInterner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PathInterner")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intern() {
+        let interner = PathInterner::new();
+        assert!(interner.is_empty());
+        let foo = interner.intern("foo");
+        let bar = interner.intern("bar");
+        assert!(foo.ptr_eq(&interner.intern(PathBuf::from("foo"))));
+        assert!(!foo.ptr_eq(&bar));
+        assert_eq!(2, interner.len());
+
+        // not interned paths are still equal
+        assert_eq!(PathArc::new("foo"), foo);
+
+        drop(bar);
+        assert_eq!(1, interner.purge());
+        assert_eq!(1, interner.len());
+        assert!(foo.ptr_eq(&interner.intern("foo")));
+
+        // typed paths reuse the interned allocation
+        let cwd = interner.intern_dir(PathDir::current_dir().unwrap());
+        let dot = interner.intern_dir(PathDir::new(".").unwrap());
+        assert!((cwd.0).0.ptr_eq(&(dot.0).0));
+        let abs = interner.intern_abs(PathAbs::new(".").unwrap());
+        assert!((cwd.0).0.ptr_eq(&abs.0));
+        assert_eq!(2, interner.len());
+
+        let global = PathInterner::global().intern("foo");
+        assert!(global.ptr_eq(&PathInterner::global().intern("foo")));
+        assert!(!global.ptr_eq(&foo));
+    }
+}
@@ -63,15 +63,22 @@
 //!   associated methods.
 //! - [`PathDir`](struct.PathDir.html): a `PathAbs` that is guaranteed to be a directory, with
 //!   associated methods.
-//! - [`PathType`](struct.PathType.html): an enum containing either a PathFile or a PathDir.
-//!   Returned by [`PathDir::list`][dir_list]
+//! - [`PathNew`](struct.PathNew.html): an absolute path that is guaranteed (on initialization)
+//!   _not_ to exist yet, inside of an existing directory. Use it to create files, directories
+//!   or symlinks.
+//! - [`PathOther`](struct.PathOther.html): a `PathAbs` that is guaranteed to be a special file,
+//!   such as a FIFO, a socket or a device.
+//! - [`PathType`](struct.PathType.html): an enum containing either a PathFile, a PathDir or a
+//!   PathOther.
+//!   Returned by [`PathDir::list`](struct.PathDir.html#method.list)
 //!
 //! In addition, all paths are serializable through serde (even on windows!) by using the crate
 //! [`stfu8`](https://crates.io/crates/stfu8) to encode/decode, allowing ill-formed UTF-16. See
 //! that crate for more details on how the resulting encoding can be edited (by hand) even in the
-//! case of what *would be* ill-formed UTF-16.
-//!
-//! [dir_list]: struct.PathDir.html#method.list)
+//! case of what *would be* ill-formed UTF-16. The encoding is also available directly through
+//! [`PathArc::to_stfu8`](struct.PathArc.html#method.to_stfu8) and
+//! [`PathArc::display_escaped`](struct.PathArc.html#method.display_escaped), which don't
+//! require the `serialize` feature (enabled by default).
 //!
 //!
 //! ## Exported File Types
@@ -88,6 +95,9 @@
 //! - [`FileEdit`](struct.FileEdit.html): a read/write file handle with `path()` attached and
 //!   improved error messages. Contains methods and trait implements for both readable _and_
 //!   writeable files.
+//! - [`FileReadBuf`](struct.FileReadBuf.html) and [`FileWriteBuf`](struct.FileWriteBuf.html):
+//!   buffered versions of `FileRead` and `FileWrite` which keep the `path()` and the improved
+//!   error messages. Created with their `buffered` methods.
 //!
 //! ### Differing Method Signatures
 //!
@@ -202,13 +212,21 @@
 //! # Ok(()) } fn main() { try_main().unwrap() }
 //! ```
 
+#[cfg(feature = "hash")]
+extern crate digest;
+extern crate filetime;
+#[cfg(feature = "compress")]
+extern crate flate2;
 #[cfg(feature = "serialize")]
 extern crate serde;
 #[macro_use]
 #[cfg(feature = "serialize")]
 extern crate serde_derive;
+#[cfg(unix)]
+extern crate libc;
+#[cfg(feature = "mmap")]
+extern crate memmap2;
 extern crate std_prelude;
-#[cfg(feature = "serialize")]
 extern crate stfu8;
 
 #[macro_use]
@@ -218,6 +236,8 @@ extern crate pretty_assertions;
 extern crate regex;
 #[cfg(test)]
 extern crate serde_json;
+#[cfg(all(test, feature = "hash"))]
+extern crate sha2;
 #[cfg(test)]
 extern crate tempdir;
 
@@ -228,22 +248,59 @@ use std_prelude::*;
 
 mod abs;
 mod arc;
+mod buf;
+mod diff;
 mod dir;
 mod edit;
+mod escape;
 mod file;
+#[cfg(feature = "hash")]
+mod hash;
+mod intern;
+mod list;
+mod lock;
+#[cfg(feature = "mmap")]
+mod mmap;
+mod new;
+mod other;
 pub mod open;
+mod rotate;
 #[cfg(feature = "serialize")]
 mod ser;
+mod stats;
+mod sync;
+mod transaction;
 mod ty;
+mod usage;
+mod utils;
+mod walk;
 mod write;
 mod read;
 
-pub use abs::PathAbs;
+pub use abs::{AncestorDirs, PathAbs};
 pub use arc::PathArc;
-pub use dir::{ListDir, PathDir};
+pub use diff::{DiffEntry, DiffMode};
+pub use dir::PathDir;
+pub use escape::DisplayEscaped;
 pub use file::PathFile;
+pub use intern::PathInterner;
+pub use list::{ListDir, ListDirs, ListFiles, ListOptions, SortBy};
+pub use lock::FileLock;
+#[cfg(feature = "mmap")]
+pub use mmap::{FileMmap, FileMmapMut};
+pub use new::PathNew;
+pub use other::{OtherKind, PathOther};
+pub use rotate::{BackupSuffix, RotateOptions};
+pub use stats::FsStats;
+pub use sync::{SyncAction, SyncOptions};
+pub use transaction::Transaction;
+#[cfg(feature = "hash")]
+pub use hash::HashMismatch;
 pub use ty::PathType;
+pub use usage::{DiskUsage, DiskUsageOptions, Usage};
+pub use walk::{WalkOptions, WalkState};
 
+pub use buf::{FileReadBuf, FileWriteBuf};
 pub use edit::FileEdit;
 pub use write::FileWrite;
 pub use read::FileRead;
@@ -261,6 +318,10 @@ pub type Result<T> = ::std::result::Result<T, Error>;
 /// To maintain good ergonomics, this type has a `impl From<Error> for std::io::Error` defined so
 /// that you may use an [`io::Result`] with methods in this crate if you don't care about accessing
 /// the underlying error data in a structured form (the pretty format will be preserved however).
+/// The structured error can be recovered with [`Error::from_io`](#method.from_io).
+///
+/// With the `serialize` feature the error implements `Serialize` and `Deserialize`, keeping the
+/// `io::ErrorKind`, the raw OS error code, the message, the action, the path(s) and the contexts.
 ///
 /// [`std::io::Error`]: https://doc.rust-lang.org/stable/std/io/struct.Error.html
 /// [`io::Result`]: https://doc.rust-lang.org/stable/std/io/type.Result.html
@@ -281,17 +342,129 @@ pub struct Error {
     io_err: io::Error,
     action: String,
     path: PathArc,
+    dest: Option<PathArc>,
+    context: Vec<ErrorContext>,
+}
+
+/// A higher-level operation during which an [`Error`](struct.Error.html) occurred, added with
+/// [`Error::context`](struct.Error.html#method.context).
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+pub struct ErrorContext {
+    action: String,
+    path: PathArc,
+    #[cfg_attr(feature = "serialize", serde(default, skip_serializing_if = "Option::is_none"))]
+    dest: Option<PathArc>,
+}
+
+impl ErrorContext {
+    /// Returns the action of the operation, i.e. `"copying tree"`.
+    pub fn action(&self) -> &str {
+        &self.action
+    }
+
+    /// Returns the path of the operation.
+    pub fn path(&self) -> &Path {
+        self.path.as_ref()
+    }
+
+    /// Returns the secondary path of the operation, if it involves two paths.
+    pub fn dest_path(&self) -> Option<&Path> {
+        self.dest.as_ref().map(|p| p.as_ref())
+    }
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_action(f, &self.action, &self.path, self.dest.as_ref())
+    }
+}
+
+fn fmt_action(
+    f: &mut fmt::Formatter,
+    action: &str,
+    path: &PathArc,
+    dest: Option<&PathArc>,
+) -> fmt::Result {
+    write!(f, "{} {}", action, path.display())?;
+    if let Some(dest) = dest {
+        write!(f, " to {}", dest.display())?;
+    }
+    Ok(())
 }
 
 impl Error {
     /// Create a new error when the path and action are known.
     pub fn new(io_err: io::Error, action: &str, path: PathArc) -> Error {
         Error {
-            io_err: io_err,
+            io_err,
             action: action.into(),
-            path: path,
+            path,
+            dest: None,
+            context: Vec::new(),
         }
     }
+
+    /// Create a new error for an action involving two paths, such as copying `path` to `dest`.
+    pub fn with_dest(io_err: io::Error, action: &str, path: PathArc, dest: PathArc) -> Error {
+        Error {
+            io_err,
+            action: action.into(),
+            path,
+            dest: Some(dest),
+            context: Vec::new(),
+        }
+    }
+
+    /// Add the higher-level operation during which the error occurred.
+    ///
+    /// Contexts are displayed after the error, from the innermost to the outermost.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// use std::io;
+    /// use path_abs::{Error, PathArc};
+    ///
+    /// # fn main() {
+    /// let err = Error::new(
+    ///     io::Error::new(io::ErrorKind::PermissionDenied, "Permission denied"),
+    ///     "opening",
+    ///     PathArc::new("/a/b/c"),
+    /// );
+    /// let err = err.context_dest("copying tree", "/a", "/b");
+    /// assert_eq!(
+    ///     "Permission denied when opening /a/b/c while copying tree /a to /b",
+    ///     err.to_string(),
+    /// );
+    /// assert_eq!("copying tree", err.contexts()[0].action());
+    /// # }
+    /// ```
+    pub fn context<P: AsRef<Path>>(mut self, action: &str, path: P) -> Error {
+        self.context.push(ErrorContext {
+            action: action.into(),
+            path: PathArc::new(path),
+            dest: None,
+        });
+        self
+    }
+
+    /// Add the higher-level operation involving two paths during which the error occurred.
+    ///
+    /// See [`context`](#method.context).
+    pub fn context_dest<P: AsRef<Path>, Q: AsRef<Path>>(
+        mut self,
+        action: &str,
+        path: P,
+        dest: Q,
+    ) -> Error {
+        self.context.push(ErrorContext {
+            action: action.into(),
+            path: PathArc::new(path),
+            dest: Some(PathArc::new(dest)),
+        });
+        self
+    }
 }
 
 impl fmt::Debug for Error {
@@ -302,13 +475,18 @@ impl fmt::Debug for Error {
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "{} when {} {}",
-            self.io_err,
-            self.action,
-            self.path.display()
-        )
+        match (self.io_err.kind(), self.io_err.raw_os_error()) {
+            // The OS message (i.e. "File exists") is confusing for directories and symlinks.
+            (io::ErrorKind::AlreadyExists, Some(code)) => {
+                write!(f, "path already exists (os error {}) when ", code)?
+            }
+            _ => write!(f, "{} when ", self.io_err)?,
+        }
+        fmt_action(f, &self.action, &self.path, self.dest.as_ref())?;
+        for context in &self.context {
+            write!(f, " while {}", context)?;
+        }
+        Ok(())
     }
 }
 
@@ -318,6 +496,18 @@ impl Error {
         self.path.as_ref()
     }
 
+    /// Returns the secondary path of actions involving two paths, i.e. the destination of
+    /// `copy` and `rename` or the link created by `symlink`.
+    pub fn dest_path(&self) -> Option<&Path> {
+        self.dest.as_ref().map(|p| p.as_ref())
+    }
+
+    /// Returns the higher-level operations during which the error occurred, from the innermost
+    /// to the outermost.
+    pub fn contexts(&self) -> &[ErrorContext] {
+        &self.context
+    }
+
     /// Returns the `std::io::Error` associated with this errors.
     pub fn io_error(&self) -> &io::Error {
         &self.io_err
@@ -327,14 +517,39 @@ impl Error {
     pub fn action(&self) -> &str {
         &self.action
     }
-}
 
-impl error::Error for Error {
-    fn description(&self) -> &str {
-        self.io_err.description()
+    /// Recover the `Error` wrapped in an `io::Error`, if there is one.
+    ///
+    /// The `std::io` trait implementations of the file types (i.e. `io::Read` for `FileRead`)
+    /// must return an `io::Error`, so they wrap this error in one. This gets it back, along with
+    /// the path and action.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::io::{self, Seek};
+    /// use path_abs::{Error, FileWrite};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let mut file = FileWrite::create(example)?;
+    ///
+    /// let io_err = file.seek(io::SeekFrom::Current(-1)).unwrap_err();
+    /// let err = Error::from_io(&io_err).unwrap();
+    /// assert_eq!(file.path().as_path(), err.path());
+    /// assert_eq!("seeking", err.action());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn from_io(err: &io::Error) -> Option<&Error> {
+        err.get_ref().and_then(|e| e.downcast_ref::<Error>())
     }
+}
 
-    fn cause(&self) -> Option<&error::Error> {
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         Some(&self.io_err)
     }
 }
@@ -391,5 +606,18 @@ mod tests {
             };
             assert_match!(pat, foo.edit().unwrap_err())
         }
+
+        {
+            let foo = PathFile::create(tmp_abs.join("foo.txt")).expect("foo.txt");
+            let dest = tmp_abs.join("missing").join("foo.txt");
+            let err = foo.copy(&dest).unwrap_err();
+            assert_eq!(Some(dest.as_path()), err.dest_path());
+            let pat = format!(
+                r"\(os error \d+\) when copying {} to {}$",
+                escape(&foo),
+                escape(&dest)
+            );
+            assert_match!(pat, err)
+        }
     }
 }
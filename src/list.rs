@@ -0,0 +1,510 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Listing the contents of directories.
+
+use std::cmp::Ordering;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::sync::Arc;
+use std::vec;
+use std_prelude::*;
+
+use super::{Error, Result};
+use super::{PathAbs, PathDir, PathFile, PathType};
+use utils::{read_dir, ReadDir};
+
+type Filter = Arc<dyn Fn(&PathType) -> bool + Send + Sync>;
+
+/// The order of the entries listed by
+/// [`PathDir::list_with`](struct.PathDir.html#method.list_with).
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub enum SortBy {
+    /// By file name.
+    Name,
+    /// By file name, comparing runs of digits as numbers so that `file2` comes before `file10`.
+    Natural,
+    /// By modification time, oldest first.
+    Mtime,
+    /// By size, smallest first.
+    Size,
+}
+
+/// Options and flags which can be used to configure how
+/// [`PathDir::list_with`](struct.PathDir.html#method.list_with) lists a directory.
+///
+/// This builder works like `std::fs::OpenOptions`: create it with `new` then chain calls to the
+/// methods to set each option.
+#[derive(Clone)]
+pub struct ListOptions {
+    sort: Option<SortBy>,
+    reverse: bool,
+    hidden: bool,
+    extensions: Vec<OsString>,
+    filters: Vec<Filter>,
+    cheap: bool,
+}
+
+impl ListOptions {
+    /// Create a blank set of options.
+    ///
+    /// By default entries are returned in the order of the filesystem, hidden entries are
+    /// included and symlinks are resolved.
+    pub fn new() -> ListOptions {
+        ListOptions {
+            sort: None,
+            reverse: false,
+            hidden: true,
+            extensions: Vec::new(),
+            filters: Vec::new(),
+            cheap: false,
+        }
+    }
+
+    /// Sort the entries. Errors are returned after all of the entries.
+    ///
+    /// > This reads the whole directory before returning the first entry.
+    pub fn sort(&mut self, sort: SortBy) -> &mut ListOptions {
+        self.sort = Some(sort);
+        self
+    }
+
+    /// Set whether to reverse the order of the sorted entries.
+    pub fn reverse(&mut self, reverse: bool) -> &mut ListOptions {
+        self.reverse = reverse;
+        self
+    }
+
+    /// Set whether to include hidden entries, i.e. those whose name starts with a `.`.
+    pub fn hidden(&mut self, hidden: bool) -> &mut ListOptions {
+        self.hidden = hidden;
+        self
+    }
+
+    /// Only list entries with the extension (i.e. `"rs"`). If called multiple times entries
+    /// with any of the extensions are listed.
+    ///
+    /// Directories are not filtered by extension.
+    pub fn extension<S: AsRef<OsStr>>(&mut self, extension: S) -> &mut ListOptions {
+        self.extensions.push(extension.as_ref().to_os_string());
+        self
+    }
+
+    /// Only list entries for which `filter` returns true. If called multiple times all of the
+    /// filters must match.
+    pub fn filter<F>(&mut self, filter: F) -> &mut ListOptions
+    where
+        F: Fn(&PathType) -> bool + Send + Sync + 'static,
+    {
+        self.filters.push(Arc::new(filter));
+        self
+    }
+
+    /// Set whether to avoid canonicalizing symlinks. Other entries are never canonicalized,
+    /// their type comes from the directory entry (which usually doesn't need any syscall).
+    ///
+    /// > **Warning**: symlinks are not resolved in this mode. The path of a symlink is the
+    /// > directory joined with its name, and its type is the one of its target.
+    pub fn cheap(&mut self, cheap: bool) -> &mut ListOptions {
+        self.cheap = cheap;
+        self
+    }
+
+    fn is_listed_name(&self, name: &OsStr) -> bool {
+        self.hidden || !name.to_string_lossy().starts_with('.')
+    }
+
+    fn is_listed(&self, ty: &PathType) -> bool {
+        if !self.extensions.is_empty() && !ty.is_dir() {
+            match ty.extension() {
+                Some(ext) if self.extensions.iter().any(|e| e == ext) => {}
+                _ => return false,
+            }
+        }
+        self.filters.iter().all(|f| f(ty))
+    }
+}
+
+impl Default for ListOptions {
+    fn default() -> ListOptions {
+        ListOptions::new()
+    }
+}
+
+impl fmt::Debug for ListOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ListOptions")
+            .field("sort", &self.sort)
+            .field("reverse", &self.reverse)
+            .field("hidden", &self.hidden)
+            .field("extensions", &self.extensions)
+            .field("filters", &self.filters.len())
+            .field("cheap", &self.cheap)
+            .finish()
+    }
+}
+
+impl PathDir {
+    /// List the contents of the directory like [`list`](#method.list) with the given options.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{ListOptions, PathDir, PathFile, SortBy};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let tmp = PathDir::new(tmp.path())?;
+    /// for name in &["img10.png", "img2.png", "img1.jpg", ".img0.png", "notes.txt"] {
+    ///     PathFile::create(tmp.join(name))?;
+    /// }
+    ///
+    /// let mut options = ListOptions::new();
+    /// options
+    ///     .sort(SortBy::Natural)
+    ///     .hidden(false)
+    ///     .extension("png")
+    ///     .extension("jpg");
+    /// let names = tmp
+    ///     .list_with(&options)?
+    ///     .map(|p| Ok(p?.file_name().unwrap().to_string_lossy().into_owned()))
+    ///     .collect::<::std::io::Result<Vec<_>>>()?;
+    /// assert_eq!(vec!["img1.jpg", "img2.png", "img10.png"], names);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn list_with(&self, options: &ListOptions) -> Result<ListDir> {
+        let fsread = read_dir(self)?;
+        let mut list = ListDir {
+            dir: self.clone(),
+            fsread,
+            options: options.clone(),
+            sorted: None,
+        };
+        if let Some(sort) = options.sort {
+            list.sorted = Some(list.sort(sort).into_iter());
+        }
+        Ok(list)
+    }
+}
+
+/// An iterator over `PathType` objects, returned by `PathDir::list`.
+pub struct ListDir {
+    // TODO: this should be a reference...?
+    // Or is this a good excuse to use Arc under the hood everywhere?
+    dir: PathDir,
+    fsread: ReadDir,
+    options: ListOptions,
+    sorted: Option<vec::IntoIter<Result<PathType>>>,
+}
+
+impl ::std::iter::Iterator for ListDir {
+    type Item = Result<PathType>;
+    fn next(&mut self) -> Option<Result<PathType>> {
+        if let Some(ref mut sorted) = self.sorted {
+            return sorted.next();
+        }
+        self.next_entry().map(|(_, ty)| ty)
+    }
+}
+
+impl ListDir {
+    /// Iterate over only the files of the directory. Errors are still returned.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// use path_abs::PathDir;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let src = PathDir::new("src")?;
+    /// for file in src.list()?.files() {
+    ///     assert!(file?.is_file());
+    /// }
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn files(self) -> ListFiles {
+        ListFiles(self)
+    }
+
+    /// Iterate over only the subdirectories of the directory. Errors are still returned.
+    pub fn dirs(self) -> ListDirs {
+        ListDirs(self)
+    }
+
+    /// The next listed entry along with its name.
+    fn next_entry(&mut self) -> Option<(OsString, Result<PathType>)> {
+        for entry in &mut self.fsread {
+            let entry = match entry {
+                Ok(e) => e,
+                Err(err) => return Some((OsString::new(), Err(err))),
+            };
+            let name = entry.file_name();
+            if !self.options.is_listed_name(&name) {
+                continue;
+            }
+            let ty = entry_type(&self.dir, &entry, !self.options.cheap);
+            match ty {
+                Ok(ref ty) if !self.options.is_listed(ty) => continue,
+                ty => return Some((name, ty)),
+            }
+        }
+        None
+    }
+
+    fn sort(&mut self, sort: SortBy) -> Vec<Result<PathType>> {
+        let mut entries = Vec::new();
+        let mut errors = Vec::new();
+        while let Some((name, ty)) = self.next_entry() {
+            match ty {
+                Ok(ty) => entries.push((name, ty)),
+                Err(err) => errors.push(Err(err)),
+            }
+        }
+        match sort {
+            SortBy::Name => entries.sort_by(|a, b| a.0.cmp(&b.0)),
+            SortBy::Natural => entries.sort_by(|a, b| natural_cmp(&a.0, &b.0)),
+            SortBy::Mtime => entries.sort_by_cached_key(|e| {
+                let mtime = e.1.metadata().ok().and_then(|m| m.modified().ok());
+                (mtime, e.0.clone())
+            }),
+            SortBy::Size => entries.sort_by_cached_key(|e| {
+                let size = e.1.metadata().ok().map(|m| m.len());
+                (size, e.0.clone())
+            }),
+        }
+        if self.options.reverse {
+            entries.reverse();
+        }
+        entries
+            .into_iter()
+            .map(|(_, ty)| Ok(ty))
+            .chain(errors)
+            .collect()
+    }
+}
+
+/// Get the `PathType` of an entry of `dir` from its file type.
+///
+/// Since `dir` is canonicalized, only symlinks need to be canonicalized (if `resolve_symlinks`)
+/// or even stat'ed.
+pub(crate) fn entry_type(
+    dir: &PathDir,
+    entry: &fs::DirEntry,
+    resolve_symlinks: bool,
+) -> Result<PathType> {
+    let path = dir.join(entry.file_name());
+    let ty = entry
+        .file_type()
+        .map_err(|err| Error::new(err, "resolving", path.clone()))?;
+    if !ty.is_symlink() {
+        PathType::from_abs_type(PathAbs(path), &ty)
+    } else if resolve_symlinks {
+        PathType::new(path)
+    } else {
+        let ty = path.metadata()?.file_type();
+        PathType::from_abs_type(PathAbs(path), &ty)
+    }
+}
+
+/// Compare the names, treating runs of ascii digits as numbers.
+fn natural_cmp(a: &OsStr, b: &OsStr) -> Ordering {
+    let (a, b) = (a.to_string_lossy(), b.to_string_lossy());
+    let (mut a, mut b) = (a.as_bytes(), b.as_bytes());
+    loop {
+        match (a.first(), b.first()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (x_num, x_rest) = split_digits(a);
+                let (y_num, y_rest) = split_digits(b);
+                // Without leading zeros, a longer number is a bigger one.
+                let (x_trim, y_trim) = (trim_zeros(x_num), trim_zeros(y_num));
+                let ord = x_trim
+                    .len()
+                    .cmp(&y_trim.len())
+                    .then_with(|| x_trim.cmp(y_trim))
+                    .then_with(|| x_num.len().cmp(&y_num.len()));
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+                a = x_rest;
+                b = y_rest;
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(y);
+                }
+                a = &a[1..];
+                b = &b[1..];
+            }
+        }
+    }
+}
+
+fn split_digits(s: &[u8]) -> (&[u8], &[u8]) {
+    let end = s
+        .iter()
+        .position(|c| !c.is_ascii_digit())
+        .unwrap_or(s.len());
+    s.split_at(end)
+}
+
+fn trim_zeros(s: &[u8]) -> &[u8] {
+    let start = s.iter().position(|&c| c != b'0').unwrap_or(s.len());
+    &s[start..]
+}
+
+/// An iterator over the files of a directory, returned by
+/// [`ListDir::files`](struct.ListDir.html#method.files).
+pub struct ListFiles(ListDir);
+
+impl ::std::iter::Iterator for ListFiles {
+    type Item = Result<PathFile>;
+    fn next(&mut self) -> Option<Result<PathFile>> {
+        for ty in &mut self.0 {
+            match ty {
+                Ok(PathType::File(f)) => return Some(Ok(f)),
+                Ok(_) => {}
+                Err(err) => return Some(Err(err)),
+            }
+        }
+        None
+    }
+}
+
+/// An iterator over the subdirectories of a directory, returned by
+/// [`ListDir::dirs`](struct.ListDir.html#method.dirs).
+pub struct ListDirs(ListDir);
+
+impl ::std::iter::Iterator for ListDirs {
+    type Item = Result<PathDir>;
+    fn next(&mut self) -> Option<Result<PathDir>> {
+        for ty in &mut self.0 {
+            match ty {
+                Ok(PathType::Dir(d)) => return Some(Ok(d)),
+                Ok(_) => {}
+                Err(err) => return Some(Err(err)),
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::super::PathArc;
+    use super::*;
+
+    fn names(list: ListDir) -> Vec<String> {
+        list.map(|p| {
+            p.unwrap()
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .into_owned()
+        })
+        .collect()
+    }
+
+    #[test]
+    fn natural_order() {
+        let mut names = vec!["a10", "a2", "a02", "b", "a1b", "a1", "", "a"];
+        names.sort_by(|a, b| natural_cmp(OsStr::new(a), OsStr::new(b)));
+        assert_eq!(vec!["", "a", "a1", "a1b", "a2", "a02", "a10", "b"], names);
+    }
+
+    #[test]
+    fn list_with() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        PathFile::create(tmp_abs.join("b.rs"))
+            .unwrap()
+            .write_str("bbb")
+            .unwrap();
+        PathFile::create(tmp_abs.join("a.txt"))
+            .unwrap()
+            .write_str("a")
+            .unwrap();
+        PathFile::create(tmp_abs.join(".c.rs"))
+            .unwrap()
+            .write_str("cc")
+            .unwrap();
+        PathDir::create(tmp_abs.join("d")).unwrap();
+
+        let mut options = ListOptions::new();
+        options.sort(SortBy::Name);
+        let list = tmp_abs.list_with(&options).unwrap();
+        assert_eq!(vec![".c.rs", "a.txt", "b.rs", "d"], names(list));
+
+        options
+            .sort(SortBy::Size)
+            .hidden(false)
+            .extension("rs")
+            .extension("txt");
+        let list = tmp_abs.list_with(&options).unwrap();
+        // the dir is not filtered by extension
+        let mut listed = names(list);
+        assert!(listed.contains(&"d".to_string()));
+        listed.retain(|n| n != "d");
+        assert_eq!(vec!["a.txt", "b.rs"], listed);
+
+        options.reverse(true).filter(|ty| ty.is_file());
+        let list = tmp_abs.list_with(&options).unwrap();
+        assert_eq!(vec!["b.rs", "a.txt"], names(list));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn list_symlinks() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let dir = PathDir::create(tmp_abs.join("dir")).unwrap();
+        let file = PathFile::create(dir.join("file")).unwrap();
+        file.symlink(tmp_abs.join("link")).unwrap();
+
+        let mut options = ListOptions::new();
+        options.sort(SortBy::Name);
+        let list: Vec<_> = tmp_abs
+            .list_with(&options)
+            .unwrap()
+            .map(|p| p.unwrap())
+            .collect();
+        // only the symlink is resolved
+        assert_eq!(vec![PathType::Dir(dir), PathType::File(file)], list);
+        assert_eq!(tmp_abs.join("dir").as_path(), list[0].as_path());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn list_cheap() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let dir = PathDir::create(tmp_abs.join("dir")).unwrap();
+        let file = PathFile::create(tmp_abs.join("file")).unwrap();
+        dir.symlink(tmp_abs.join("link")).unwrap();
+
+        let mut options = ListOptions::new();
+        options.sort(SortBy::Name).cheap(true);
+        let list: Vec<_> = tmp_abs
+            .list_with(&options)
+            .unwrap()
+            .map(|p| p.unwrap())
+            .collect();
+        let link = PathDir::from_abs_unchecked(PathAbs(PathArc::new(tmp_abs.join("link"))));
+        let expected = vec![
+            PathType::Dir(dir),
+            PathType::File(file),
+            PathType::Dir(link),
+        ];
+        assert_eq!(expected, list);
+    }
+}
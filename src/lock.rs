@@ -0,0 +1,376 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Lockfiles (aka pidfiles).
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std_prelude::*;
+
+use super::{Error, Result};
+use super::{FileWrite, PathArc, PathFile};
+
+/// A lockfile held by this process, created with
+/// [`PathFile::lockfile`](struct.PathFile.html#method.lockfile).
+///
+/// The file is removed when this is dropped, unless it was replaced in the meantime. Use
+/// [`release`](#method.release) to handle errors from removing it.
+pub struct FileLock {
+    file: PathFile,
+    owner: Owner,
+    released: bool,
+}
+
+/// The process which created a lockfile.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Owner {
+    pid: u32,
+    host: String,
+    timestamp: u64,
+}
+
+impl PathFile {
+    /// Atomically create a lockfile at `path`, writing the pid and hostname of this process and
+    /// the current time (in seconds since the unix epoch) to it, one per line.
+    ///
+    /// Returns `io::ErrorKind::AlreadyExists` if the lock is held. A lock is _stale_ if it was
+    /// created on this host by a process which no longer exists, in which case it is replaced.
+    ///
+    /// > Whether a process exists can only be checked on unix, so on other platforms locks are
+    /// > never considered stale.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::io;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let tmp = PathDir::new(tmp.path())?;
+    /// let path = tmp.join("cron.lock");
+    /// {
+    ///     let lock = PathFile::lockfile(&path)?;
+    ///     assert!(lock.path().exists());
+    ///
+    ///     let err = PathFile::lockfile(&path).unwrap_err();
+    ///     assert_eq!(io::ErrorKind::AlreadyExists, err.io_error().kind());
+    /// }
+    /// // the lock is released when dropped
+    /// assert!(!path.exists());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn lockfile<P: AsRef<Path>>(path: P) -> Result<FileLock> {
+        let path = path.as_ref();
+        let owner = Owner::current();
+        let mut replaced_stale = false;
+        loop {
+            let mut f = match FileWrite::create_new(path) {
+                Ok(f) => f,
+                Err(ref err)
+                    if err.io_error().kind() == io::ErrorKind::AlreadyExists && !replaced_stale =>
+                {
+                    replaced_stale = true;
+                    match Owner::read(path) {
+                        Some(ref holder) if holder.is_stale() => {
+                            if remove_stale(path, holder)? {
+                                continue;
+                            }
+                            return Err(held(path, Owner::read(path)));
+                        }
+                        holder => return Err(held(path, holder)),
+                    }
+                }
+                Err(ref err) if err.io_error().kind() == io::ErrorKind::AlreadyExists => {
+                    return Err(held(path, Owner::read(path)));
+                }
+                Err(err) => return Err(err),
+            };
+            let written = f.write_str(&owner.to_string()).and_then(|_| f.sync_all());
+            if let Err(err) = written {
+                // The file was created by us but doesn't hold our owner, remove it so the lock
+                // isn't held forever.
+                let _ = fs::remove_file(path);
+                return Err(err);
+            }
+            return Ok(FileLock {
+                file: f.path().clone(),
+                owner,
+                released: false,
+            });
+        }
+    }
+}
+
+impl FileLock {
+    /// The path of the lockfile.
+    pub fn path(&self) -> &PathFile {
+        &self.file
+    }
+
+    /// Release the lock by removing the file.
+    ///
+    /// Returns `io::ErrorKind::NotFound` if the file no longer belongs to this lock, i.e.
+    /// because it was removed by someone else.
+    pub fn release(mut self) -> Result<()> {
+        self.released = true;
+        if !self.is_owned() {
+            return Err(Error::new(
+                io::Error::new(io::ErrorKind::NotFound, "lock is no longer held"),
+                "releasing lock",
+                self.file.clone().into(),
+            ));
+        }
+        self.file.clone().remove()
+    }
+
+    /// Whether the file still belongs to this lock.
+    fn is_owned(&self) -> bool {
+        Owner::read(&self.file).as_ref() == Some(&self.owner)
+    }
+}
+
+impl fmt::Debug for FileLock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FileLock(")?;
+        self.file.fmt(f)?;
+        write!(f, ")")
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        if !self.released && self.is_owned() {
+            let _ = fs::remove_file(&self.file);
+        }
+    }
+}
+
+impl Owner {
+    fn current() -> Owner {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Owner {
+            pid: process::id(),
+            host: hostname(),
+            timestamp,
+        }
+    }
+
+    /// Read the owner of an existing lockfile. Returns `None` if it can't be read or parsed,
+    /// i.e. because the owner is still writing it.
+    fn read(path: &Path) -> Option<Owner> {
+        let content = fs::read_to_string(path).ok()?;
+        let mut lines = content.lines();
+        let pid = lines.next()?.parse().ok()?;
+        let host = lines.next()?.to_string();
+        let timestamp = lines.next()?.parse().ok()?;
+        Some(Owner {
+            pid,
+            host,
+            timestamp,
+        })
+    }
+
+    fn is_stale(&self) -> bool {
+        self.host == hostname() && !pid_exists(self.pid)
+    }
+}
+
+impl fmt::Display for Owner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.pid)?;
+        writeln!(f, "{}", self.host)?;
+        writeln!(f, "{}", self.timestamp)
+    }
+}
+
+fn held(path: &Path, holder: Option<Owner>) -> Error {
+    let msg = match holder {
+        Some(h) => format!(
+            "lock is held by pid {} on {} since {}",
+            h.pid, h.host, h.timestamp
+        ),
+        None => "lock is held".to_string(),
+    };
+    Error::new(
+        io::Error::new(io::ErrorKind::AlreadyExists, msg),
+        "locking",
+        PathArc::new(path),
+    )
+}
+
+/// Remove the lockfile if it is still held by the `stale` owner. Returns whether the lock can
+/// be taken, i.e. `false` if another process replaced the stale lock first.
+///
+/// The file is first moved out of the way, so that a fresh lock created by another process
+/// after `stale` was read is never removed. If the moved file turns out to be a fresh lock it
+/// is put back with a hard link, which fails instead of replacing a newer lock.
+fn remove_stale(path: &Path, stale: &Owner) -> Result<bool> {
+    let mut moved = path.as_os_str().to_os_string();
+    moved.push(format!(".stale.{}", process::id()));
+    let moved = PathArc::new(moved);
+    match fs::rename(path, &moved) {
+        // Someone else removed it first.
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(true),
+        r => r.map_err(|err| {
+            Error::with_dest(err, "moving stale lock", PathArc::new(path), moved.clone())
+        })?,
+    }
+    let still_stale = Owner::read(&moved).as_ref() == Some(stale);
+    if !still_stale {
+        restore(&moved, path)?;
+        return Ok(false);
+    }
+    fs::remove_file(&moved).map_err(|err| Error::new(err, "removing stale lock", moved))?;
+    Ok(true)
+}
+
+/// Put the fresh lock which was `moved` aside back at `path`. If yet another process took the
+/// lock in the meantime its lock is kept, the lock is held either way.
+fn restore(moved: &PathArc, path: &Path) -> Result<()> {
+    let restored = fs::hard_link(moved, path);
+    let _ = fs::remove_file(moved);
+    match restored {
+        Err(ref err) if err.kind() == io::ErrorKind::AlreadyExists => Ok(()),
+        r => r.map_err(|err| {
+            Error::with_dest(err, "restoring lock", moved.clone(), PathArc::new(path))
+        }),
+    }
+}
+
+#[cfg(unix)]
+fn pid_exists(pid: u32) -> bool {
+    use libc;
+
+    // `kill` signals a process group for 0 and negative pids, and no process can have them.
+    if pid == 0 || pid > libc::pid_t::MAX as u32 {
+        return false;
+    }
+    // Signal 0 only checks whether the process exists. EPERM means it belongs to another user.
+    let ret = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    ret == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn pid_exists(_pid: u32) -> bool {
+    true
+}
+
+#[cfg(unix)]
+fn hostname() -> String {
+    use libc;
+
+    let mut buf = [0u8; 256];
+    let ret = unsafe { libc::gethostname(buf.as_mut_ptr() as *mut _, buf.len()) };
+    if ret != 0 {
+        return String::new();
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
+
+#[cfg(not(unix))]
+fn hostname() -> String {
+    ::std::env::var("COMPUTERNAME").unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::super::PathDir;
+    use super::*;
+
+    #[test]
+    fn lockfile() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let path = tmp_abs.join("foo.lock");
+
+        let lock = PathFile::lockfile(&path).unwrap();
+        let owner = Owner::read(&path).unwrap();
+        assert_eq!(process::id(), owner.pid);
+        assert_eq!(hostname(), owner.host);
+
+        let err = PathFile::lockfile(&path).unwrap_err();
+        assert_eq!(io::ErrorKind::AlreadyExists, err.io_error().kind());
+        let expected = format!("lock is held by pid {} on {}", owner.pid, owner.host);
+        assert!(err.to_string().starts_with(&expected));
+
+        lock.release().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn lockfile_stale() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let path = tmp_abs.join("foo.lock");
+
+        let mut child = process::Command::new("true").spawn().unwrap();
+        let dead = child.id();
+        child.wait().unwrap();
+        assert!(!pid_exists(dead));
+
+        // held by a process on another host, which can't be checked
+        let other = PathFile::create(&path).unwrap();
+        other
+            .write_str(&format!("{}\nother-host\n0\n", dead))
+            .unwrap();
+        assert!(PathFile::lockfile(&path).is_err());
+
+        other
+            .write_str(&format!("{}\n{}\n0\n", dead, hostname()))
+            .unwrap();
+        let lock = PathFile::lockfile(&path).unwrap();
+        assert_eq!(process::id(), Owner::read(&path).unwrap().pid);
+        drop(lock);
+        assert!(!path.exists());
+
+        assert!(!pid_exists(0));
+        assert!(!pid_exists(u32::MAX));
+    }
+
+    #[test]
+    fn lockfile_replaced() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let path = tmp_abs.join("foo.lock");
+
+        // another process replaced the stale lock after it was read
+        let lock = PathFile::lockfile(&path).unwrap();
+        let stale = Owner {
+            pid: 1,
+            host: hostname(),
+            timestamp: 0,
+        };
+        assert!(!remove_stale(&path, &stale).unwrap());
+        assert_eq!(Some(lock.owner.clone()), Owner::read(&path));
+        assert_eq!(1, tmp_abs.list().unwrap().count());
+
+        // a lock taken while the fresh one was moved aside is kept
+        let moved = PathArc::new(tmp_abs.join("foo.lock.moved"));
+        PathFile::create(&moved).unwrap().write_str("moved").unwrap();
+        restore(&moved, &path).unwrap();
+        assert_eq!(Some(lock.owner.clone()), Owner::read(&path));
+        assert!(!moved.exists());
+
+        // a replaced lock is not removed when dropped
+        let other = format!("{}\n{}\n0\n", stale.pid, stale.host);
+        PathFile::new(&path).unwrap().write_str(&other).unwrap();
+        drop(lock);
+        assert_eq!(Some(stale), Owner::read(&path));
+    }
+}
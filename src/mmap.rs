@@ -0,0 +1,264 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Memory-mapped access to files.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std_prelude::*;
+
+use memmap2::{Mmap, MmapMut, MmapOptions};
+
+use super::{Error, Result};
+use super::{FileEdit, PathFile};
+
+/// A read-only memory map of a file, returned by
+/// [`PathFile::mmap`](struct.PathFile.html#method.mmap).
+///
+/// Dereferences to the contents of the file as a `[u8]`.
+pub struct FileMmap {
+    path: PathFile,
+    // `None` for empty files, which cannot be mapped.
+    map: Option<Mmap>,
+}
+
+impl FileMmap {
+    /// Get the path of the mapped file.
+    pub fn path(&self) -> &PathFile {
+        &self.path
+    }
+}
+
+impl fmt::Debug for FileMmap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FileMmap(")?;
+        self.path.fmt(f)?;
+        write!(f, ")")
+    }
+}
+
+impl Deref for FileMmap {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self.map {
+            Some(ref map) => map,
+            None => &[],
+        }
+    }
+}
+
+impl AsRef<[u8]> for FileMmap {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+/// A writeable memory map of a file, returned by
+/// [`FileEdit::mmap_mut`](struct.FileEdit.html#method.mmap_mut).
+///
+/// Dereferences to the contents of the file as a `[u8]`. The file handle is borrowed for the
+/// lifetime of the map so it cannot be written to (or resized) at the same time.
+pub struct FileMmapMut<'a> {
+    edit: &'a mut FileEdit,
+    // `None` for empty files, which cannot be mapped.
+    map: Option<MmapMut>,
+}
+
+impl<'a> FileMmapMut<'a> {
+    /// Get the path of the mapped file.
+    pub fn path(&self) -> &PathFile {
+        self.edit.path()
+    }
+
+    /// Flush the outstanding changes of the map to the file.
+    ///
+    /// Changes are also written back when the map is dropped, but without any way of knowing
+    /// whether it succeeded.
+    pub fn flush(&self) -> Result<()> {
+        match self.map {
+            Some(ref map) => map
+                .flush()
+                .map_err(|err| Error::new(err, "flushing map of", self.path().clone().into())),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'a> fmt::Debug for FileMmapMut<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FileMmapMut(")?;
+        self.path().fmt(f)?;
+        write!(f, ")")
+    }
+}
+
+impl<'a> Deref for FileMmapMut<'a> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self.map {
+            Some(ref map) => map,
+            None => &[],
+        }
+    }
+}
+
+impl<'a> DerefMut for FileMmapMut<'a> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        match self.map {
+            Some(ref mut map) => map,
+            None => &mut [],
+        }
+    }
+}
+
+impl PathFile {
+    /// Map the file into memory as read-only.
+    ///
+    /// Empty files are supported and map to an empty slice.
+    ///
+    /// # Safety
+    ///
+    /// If the file is modified (by this or another process) while it is mapped, the contents of
+    /// the map change underneath the `&[u8]`, which is undefined behavior. If it is truncated,
+    /// accessing the map may crash the process with `SIGBUS`. The caller must ensure the file
+    /// is not modified while it is mapped, i.e. by using a lockfile or private files.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    /// file.write_str("foo\nbar")?;
+    ///
+    /// let map = unsafe { file.mmap()? };
+    /// assert_eq!(b"foo\nbar", &map[..]);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub unsafe fn mmap(&self) -> Result<FileMmap> {
+        let read = self.read()?;
+        let len = map_len(self, &read.0.file)?;
+        let map = if len == 0 {
+            None
+        } else {
+            // SAFETY: the caller guarantees the file is not modified while it is mapped.
+            let map = unsafe { MmapOptions::new().len(len).map(&read.0.file) }
+                .map_err(|err| Error::new(err, "memory mapping", self.clone().into()))?;
+            check_len(self, &read.0.file, len)?;
+            Some(map)
+        };
+        Ok(FileMmap {
+            path: self.clone(),
+            map,
+        })
+    }
+}
+
+impl FileEdit {
+    /// Map the file into memory as writeable. Changes to the map are written to the file.
+    ///
+    /// The map has the length of the file at the time it is created; use `set_len` first to
+    /// grow the file. Empty files are supported and map to an empty slice.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure the file is not modified (other than through the map) while it
+    /// is mapped, see [`PathFile::mmap`](struct.PathFile.html#method.mmap).
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    /// file.write_str("foo")?;
+    ///
+    /// let mut edit = file.edit()?;
+    /// {
+    ///     let mut map = unsafe { edit.mmap_mut()? };
+    ///     map[0] = b'b';
+    ///     map.flush()?;
+    /// }
+    /// assert_eq!("boo", file.read_string()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub unsafe fn mmap_mut<'a>(&'a mut self) -> Result<FileMmapMut<'a>> {
+        let len = map_len(self.path(), &self.0.file)?;
+        let map = if len == 0 {
+            None
+        } else {
+            // SAFETY: the caller guarantees the file is not modified while it is mapped.
+            let map = unsafe { MmapOptions::new().len(len).map_mut(&self.0.file) }
+                .map_err(|err| Error::new(err, "memory mapping", self.path().clone().into()))?;
+            check_len(self.path(), &self.0.file, len)?;
+            Some(map)
+        };
+        Ok(FileMmapMut { edit: self, map })
+    }
+}
+
+/// Get the length of the file as a `usize`.
+fn map_len(path: &PathFile, file: &fs::File) -> Result<usize> {
+    let len = file
+        .metadata()
+        .map_err(|err| Error::new(err, "getting metadata for", path.clone().into()))?
+        .len();
+    if len > usize::MAX as u64 {
+        return Err(Error::new(
+            io::Error::new(io::ErrorKind::InvalidData, "file is too large to map"),
+            "memory mapping",
+            path.clone().into(),
+        ));
+    }
+    Ok(len as usize)
+}
+
+/// Check that the file was not truncated while it was being mapped.
+fn check_len(path: &PathFile, file: &fs::File, len: usize) -> Result<()> {
+    if map_len(path, file)? < len {
+        return Err(Error::new(
+            io::Error::new(io::ErrorKind::UnexpectedEof, "file was truncated while mapping"),
+            "memory mapping",
+            path.clone().into(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::super::{PathDir, PathFile};
+
+    #[test]
+    fn mmap_empty() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("empty.txt")).unwrap();
+
+        assert!(unsafe { file.mmap() }.unwrap().is_empty());
+
+        let mut edit = file.edit().unwrap();
+        let map = unsafe { edit.mmap_mut() }.unwrap();
+        assert!(map.is_empty());
+        map.flush().unwrap();
+    }
+}
@@ -0,0 +1,235 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Paths which do not exist yet.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std_prelude::*;
+
+use super::{Error, Result};
+use super::{PathAbs, PathArc, PathDir, PathFile};
+use dir::symlink_dir;
+use file::symlink_file;
+
+#[derive(Clone, Eq, Hash, PartialEq, PartialOrd, Ord)]
+/// An absolute path that did not exist at the time of initialization, but whose parent was an
+/// existing directory. Use this for paths that are about to be created, such as output files.
+///
+/// The parent is canonicalized, but the leaf is kept as is.
+pub struct PathNew {
+    parent: PathDir,
+    path: PathArc,
+}
+
+impl PathNew {
+    /// Instantiate a new `PathNew`.
+    ///
+    /// Returns `io::ErrorKind::AlreadyExists` if the path exists (even as a broken symlink) and
+    /// `io::ErrorKind::InvalidInput` if it has no file name (i.e. it ends with `..`). The parent
+    /// must be an existing directory.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::io;
+    /// use path_abs::{PathDir, PathNew};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let tmp = PathDir::new(tmp.path())?;
+    /// let out = PathNew::new(tmp.join("out.txt"))?;
+    /// let file = out.create_file()?;
+    /// file.write_str("done")?;
+    ///
+    /// let err = PathNew::new(tmp.join("out.txt")).unwrap_err();
+    /// assert_eq!(io::ErrorKind::AlreadyExists, err.io_error().kind());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<PathNew> {
+        let path = path.as_ref();
+        let name = match path.file_name() {
+            Some(name) => name,
+            None => {
+                return Err(Error::new(
+                    io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"),
+                    "resolving",
+                    PathArc::new(path),
+                ))
+            }
+        };
+        let parent = match path.parent() {
+            Some(p) if p != Path::new("") => PathDir::new(p)?,
+            _ => PathDir::current_dir()?,
+        };
+        let path = PathArc::new(parent.join(name));
+        match path.symlink_metadata() {
+            Ok(_) => Err(Error::new(
+                io::Error::new(io::ErrorKind::AlreadyExists, "path already exists"),
+                "resolving",
+                path,
+            )),
+            Err(ref err) if err.io_error().kind() == io::ErrorKind::NotFound => {
+                Ok(PathNew { parent, path })
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// The directory the path will be created in.
+    pub fn parent_dir(&self) -> &PathDir {
+        &self.parent
+    }
+
+    /// Return a reference to a basic `std::path::Path`
+    pub fn as_path(&self) -> &Path {
+        self.as_ref()
+    }
+
+    /// Create an empty file at the path.
+    ///
+    /// Returns `io::ErrorKind::AlreadyExists` if the path was created since `self` was
+    /// constructed.
+    pub fn create_file(self) -> Result<PathFile> {
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&self.path)
+            .map_err(|err| Error::new(err, "creating", self.path.clone()))?;
+        Ok(PathFile::from_abs_unchecked(PathAbs(self.path)))
+    }
+
+    /// Create a directory at the path.
+    ///
+    /// Returns `io::ErrorKind::AlreadyExists` if the path was created since `self` was
+    /// constructed.
+    pub fn create_dir(self) -> Result<PathDir> {
+        fs::create_dir(&self.path)
+            .map_err(|err| Error::new(err, "creating", self.path.clone()))?;
+        Ok(PathDir::from_abs_unchecked(PathAbs(self.path)))
+    }
+
+    /// Create a symlink at the path pointing to `target`. A relative `target` is relative to
+    /// the parent directory.
+    ///
+    /// Returns `io::ErrorKind::AlreadyExists` if the path was created since `self` was
+    /// constructed.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile, PathNew};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let tmp = PathDir::new(tmp.path())?;
+    /// let file = PathFile::create(tmp.join("foo.txt"))?;
+    /// let link = PathNew::new(tmp.join("foo.link"))?;
+    /// link.create_symlink("foo.txt")?;
+    /// assert_eq!(file, PathFile::new(tmp.join("foo.link"))?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn create_symlink<P: AsRef<Path>>(self, target: P) -> Result<()> {
+        let target = target.as_ref();
+        // Only windows cares whether the target is a directory.
+        let is_dir = self.parent.join(target).is_dir();
+        let created = if is_dir {
+            symlink_dir(target, &self.path)
+        } else {
+            symlink_file(target, &self.path)
+        };
+        created.map_err(|err| {
+            Error::with_dest(err, "symlinking", PathArc::new(target), self.path)
+        })
+    }
+}
+
+impl fmt::Debug for PathNew {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.path.fmt(f)
+    }
+}
+
+impl AsRef<PathArc> for PathNew {
+    fn as_ref(&self) -> &PathArc {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for PathNew {
+    fn as_ref(&self) -> &Path {
+        self.path.as_ref()
+    }
+}
+
+impl AsRef<PathBuf> for PathNew {
+    fn as_ref(&self) -> &PathBuf {
+        self.path.as_ref()
+    }
+}
+
+impl Deref for PathNew {
+    type Target = PathArc;
+
+    fn deref(&self) -> &PathArc {
+        &self.path
+    }
+}
+
+impl From<PathNew> for PathArc {
+    fn from(path: PathNew) -> PathArc {
+        path.path
+    }
+}
+
+impl From<PathNew> for PathBuf {
+    fn from(path: PathNew) -> PathBuf {
+        path.path.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use tempdir::TempDir;
+
+    use super::super::{PathDir, PathFile};
+    use super::*;
+
+    #[test]
+    fn sanity_new() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+
+        let foo = PathNew::new(tmp_abs.join("foo")).unwrap();
+        assert_eq!(&tmp_abs, foo.parent_dir());
+        let dup = foo.clone();
+        let foo_dir = foo.create_dir().unwrap();
+        assert!(foo_dir.is_dir());
+
+        // it was created since `dup` was constructed
+        let err = dup.create_file().unwrap_err();
+        assert_eq!(io::ErrorKind::AlreadyExists, err.io_error().kind());
+
+        let err = PathNew::new(foo_dir.join("..")).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, err.io_error().kind());
+        let err = PathNew::new(tmp_abs.join("missing").join("bar")).unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.io_error().kind());
+
+        // broken symlinks exist too
+        let link = PathNew::new(tmp_abs.join("link")).unwrap();
+        link.create_symlink("missing").unwrap();
+        let err = PathNew::new(tmp_abs.join("link")).unwrap_err();
+        assert_eq!(io::ErrorKind::AlreadyExists, err.io_error().kind());
+
+        let file = PathNew::new(foo_dir.join("bar.txt")).unwrap().create_file().unwrap();
+        assert_eq!(file, PathFile::new(foo_dir.join("bar.txt")).unwrap());
+    }
+}
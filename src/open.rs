@@ -31,8 +31,8 @@ impl FileOpen {
 
         let path = PathFile::new(path)?;
         Ok(FileOpen {
-            path: path,
-            file: file,
+            path,
+            file,
         })
     }
 
@@ -46,8 +46,8 @@ impl FileOpen {
             .map_err(|err| Error::new(err, "opening", path.clone().into()))?;
 
         Ok(FileOpen {
-            path: path,
-            file: file,
+            path,
+            file,
         })
     }
 
@@ -79,7 +79,7 @@ impl FileOpen {
             .try_clone()
             .map_err(|err| Error::new(err, "cloning file handle for", self.path.clone().into()))?;
         Ok(FileOpen {
-            file: file,
+            file,
             path: self.path.clone(),
         })
     }
@@ -93,8 +93,8 @@ impl fmt::Debug for FileOpen {
     }
 }
 
-impl Into<fs::File> for FileOpen {
-    fn into(self) -> fs::File {
-        self.file
+impl From<FileOpen> for fs::File {
+    fn from(open: FileOpen) -> fs::File {
+        open.file
     }
 }
@@ -0,0 +1,227 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Paths which are neither files nor directories.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std_prelude::*;
+
+use super::{Error, Result};
+use super::{PathAbs, PathArc};
+
+/// The kind of a [`PathOther`](struct.PathOther.html).
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub enum OtherKind {
+    /// A named pipe.
+    Fifo,
+    /// A unix domain socket.
+    Socket,
+    /// A block device, i.e. a disk.
+    BlockDevice,
+    /// A character device, i.e. a terminal or `/dev/null`.
+    CharDevice,
+}
+
+impl OtherKind {
+    /// Get the kind of the file type, or `None` if it is not one of the special kinds.
+    #[cfg(unix)]
+    pub(crate) fn from_file_type(ty: &fs::FileType) -> Option<OtherKind> {
+        use std::os::unix::fs::FileTypeExt;
+
+        if ty.is_fifo() {
+            Some(OtherKind::Fifo)
+        } else if ty.is_socket() {
+            Some(OtherKind::Socket)
+        } else if ty.is_block_device() {
+            Some(OtherKind::BlockDevice)
+        } else if ty.is_char_device() {
+            Some(OtherKind::CharDevice)
+        } else {
+            None
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn from_file_type(_ty: &fs::FileType) -> Option<OtherKind> {
+        None
+    }
+}
+
+impl fmt::Display for OtherKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            OtherKind::Fifo => "FIFO",
+            OtherKind::Socket => "socket",
+            OtherKind::BlockDevice => "block device",
+            OtherKind::CharDevice => "character device",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, Eq, Hash, PartialEq, PartialOrd, Ord)]
+/// a `PathAbs` that was a special file (i.e. a FIFO, socket or device) at the time of
+/// initialization.
+///
+/// These are returned as `PathType::Other` when listing directories such as `/dev` or `/run`.
+pub struct PathOther {
+    abs: PathAbs,
+    kind: OtherKind,
+}
+
+impl PathOther {
+    /// Instantiate a new `PathOther`. The path must exist and be a special file or `io::Error`
+    /// will be returned.
+    ///
+    /// Returns `io::ErrorKind::InvalidInput` if the path exists but is a file or a directory.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// use path_abs::{OtherKind, PathOther};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// # if cfg!(unix) {
+    /// let null = PathOther::new("/dev/null")?;
+    /// assert_eq!(OtherKind::CharDevice, null.kind());
+    /// # }
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<PathOther> {
+        let abs = PathAbs::new(path)?;
+        PathOther::from_abs(abs)
+    }
+
+    /// Consume the `PathAbs` validating that the path is a special file and returning
+    /// `PathOther`.
+    pub fn from_abs(abs: PathAbs) -> Result<PathOther> {
+        let ty = abs.metadata()?.file_type();
+        match OtherKind::from_file_type(&ty) {
+            Some(kind) => Ok(PathOther { abs, kind }),
+            None => Err(Error::new(
+                io::Error::new(io::ErrorKind::InvalidInput, "path is a file or a dir"),
+                "resolving",
+                abs.into(),
+            )),
+        }
+    }
+
+    /// Do the conversion _without checking_.
+    pub(crate) fn from_abs_unchecked(abs: PathAbs, kind: OtherKind) -> PathOther {
+        PathOther { abs, kind }
+    }
+
+    /// The kind of special file.
+    pub fn kind(&self) -> OtherKind {
+        self.kind
+    }
+
+    /// Return a reference to a basic `std::path::Path`
+    pub fn as_path(&self) -> &Path {
+        self.as_ref()
+    }
+}
+
+/// The message for an error when `path` is not the `expected` type, which names its actual type
+/// if it is a special file.
+pub(crate) fn not_a(expected: &str, path: &Path) -> String {
+    let kind = fs::metadata(path)
+        .ok()
+        .and_then(|m| OtherKind::from_file_type(&m.file_type()));
+    match kind {
+        Some(kind) => format!("path is a {}, not a {}", kind, expected),
+        None => format!("path is not a {}", expected),
+    }
+}
+
+impl AsRef<PathAbs> for PathOther {
+    fn as_ref(&self) -> &PathAbs {
+        &self.abs
+    }
+}
+
+impl AsRef<PathArc> for PathOther {
+    fn as_ref(&self) -> &PathArc {
+        &self.abs
+    }
+}
+
+impl AsRef<Path> for PathOther {
+    fn as_ref(&self) -> &Path {
+        self.abs.as_ref()
+    }
+}
+
+impl AsRef<PathBuf> for PathOther {
+    fn as_ref(&self) -> &PathBuf {
+        self.abs.as_ref()
+    }
+}
+
+impl Deref for PathOther {
+    type Target = PathAbs;
+
+    fn deref(&self) -> &PathAbs {
+        &self.abs
+    }
+}
+
+impl From<PathOther> for PathAbs {
+    /// Downgrades the `PathOther` into a `PathAbs`
+    fn from(path: PathOther) -> PathAbs {
+        path.abs
+    }
+}
+
+impl From<PathOther> for PathArc {
+    /// Downgrades the `PathOther` into a `PathArc`
+    fn from(path: PathOther) -> PathArc {
+        path.abs.into()
+    }
+}
+
+impl From<PathOther> for PathBuf {
+    /// Downgrades the `PathOther` into a `PathBuf`. Avoids a clone if this is the only reference.
+    fn from(path: PathOther) -> PathBuf {
+        let arc: PathArc = path.into();
+        arc.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::super::{PathDir, PathFile, PathType};
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn sanity_other() {
+        use std::os::unix::net::UnixListener;
+
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let sock_path = tmp_abs.join("foo.sock");
+        let _listener = UnixListener::bind(&sock_path).unwrap();
+
+        let sock = PathOther::new(&sock_path).unwrap();
+        assert_eq!(OtherKind::Socket, sock.kind());
+        assert_eq!(PathType::Other(sock.clone()), PathType::new(&sock_path).unwrap());
+
+        let listed: Vec<_> = tmp_abs.list().unwrap().map(|p| p.unwrap()).collect();
+        assert_eq!(vec![PathType::Other(sock)], listed);
+
+        let err = PathFile::new(&sock_path).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, err.io_error().kind());
+        assert!(err.to_string().starts_with("path is a socket, not a file"));
+        let err = PathDir::new(&sock_path).unwrap_err();
+        assert!(err.to_string().starts_with("path is a socket, not a dir"));
+    }
+}
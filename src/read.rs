@@ -79,23 +79,19 @@ impl fmt::Debug for FileRead {
 
 impl io::Read for FileRead {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.0.file.read(buf).map_err(|err| {
-            io::Error::new(
-                err.kind(),
-                format!("{} when reading {}", err, self.path().display()),
-            )
-        })
+        self.0
+            .file
+            .read(buf)
+            .map_err(|err| Error::new(err, "reading", self.path.clone().into()).into())
     }
 }
 
 impl io::Seek for FileRead {
     fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
-        self.0.file.seek(pos).map_err(|err| {
-            io::Error::new(
-                err.kind(),
-                format!("{} seeking {}", err, self.path().display()),
-            )
-        })
+        self.0
+            .file
+            .seek(pos)
+            .map_err(|err| Error::new(err, "seeking", self.path.clone().into()).into())
     }
 }
 
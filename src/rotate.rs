@@ -0,0 +1,530 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Keeping old versions of files around.
+
+use std::fs;
+use std::io;
+use std_prelude::*;
+
+use super::{Error, Result};
+use super::{PathAbs, PathArc, PathFile};
+use utils::{read_dir, temp_path};
+
+/// The suffix of the backup created by
+/// [`PathFile::write_with_backup`](struct.PathFile.html#method.write_with_backup).
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+pub enum BackupSuffix {
+    /// Append a fixed suffix such as `~` or `.bak`, replacing any previous backup.
+    Fixed(String),
+    /// Append `.1`, `.2`, etc, using the first one which does not exist yet. An existing backup
+    /// is never replaced, even by concurrent writers.
+    Numbered,
+}
+
+impl BackupSuffix {
+    /// Copy `file` to its next backup, returning the backup.
+    fn backup(&self, file: &PathFile) -> Result<PathFile> {
+        match *self {
+            BackupSuffix::Fixed(ref suffix) => {
+                // Copy next to the backup and rename it into place, so the previous backup is
+                // never left partially overwritten.
+                let backup = PathArc::new(with_suffix(file, suffix));
+                let tmp = temp_path(&backup)?;
+                let copied = copy_new(file, &tmp).and_then(|_| {
+                    fs::rename(&tmp, &backup).map_err(|err| {
+                        Error::with_dest(err, "renaming", tmp.clone(), backup.clone())
+                    })
+                });
+                if copied.is_err() {
+                    let _ = fs::remove_file(&tmp);
+                }
+                copied?;
+                Ok(PathFile::from_abs_unchecked(PathAbs(backup)))
+            }
+            BackupSuffix::Numbered => {
+                let mut n = 1;
+                loop {
+                    let backup = PathArc::new(with_suffix(file, &format!(".{}", n)));
+                    match copy_new(file, &backup) {
+                        // Taken, possibly by another writer since the previous attempt.
+                        Err(ref err) if err.io_error().kind() == io::ErrorKind::AlreadyExists => {
+                            n += 1
+                        }
+                        copied => return copied,
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Default for BackupSuffix {
+    /// The `~` suffix used by most editors.
+    fn default() -> BackupSuffix {
+        BackupSuffix::Fixed("~".into())
+    }
+}
+
+/// Options which can be used to configure how
+/// [`PathFile::rotate_with`](struct.PathFile.html#method.rotate_with) rotates a file.
+#[derive(Debug, Clone)]
+pub struct RotateOptions {
+    create: bool,
+    compress: bool,
+}
+
+impl RotateOptions {
+    /// Create a blank set of options.
+    pub fn new() -> RotateOptions {
+        RotateOptions::default()
+    }
+
+    /// Set whether to create a new empty file (with the same permissions) in place of the
+    /// rotated one. This is `true` by default.
+    pub fn create(&mut self, create: bool) -> &mut RotateOptions {
+        self.create = create;
+        self
+    }
+
+    /// Set whether to gzip older generations, i.e. `.2.gz`, `.3.gz`, etc. The newest
+    /// generation (`.1`) is never compressed since processes may still be writing to it.
+    ///
+    /// If a generation exists both compressed and uncompressed, the uncompressed one is shifted
+    /// without compressing it rather than replacing the compressed one.
+    #[cfg(feature = "compress")]
+    pub fn compress(&mut self, compress: bool) -> &mut RotateOptions {
+        self.compress = compress;
+        self
+    }
+}
+
+impl Default for RotateOptions {
+    fn default() -> RotateOptions {
+        RotateOptions {
+            create: true,
+            compress: false,
+        }
+    }
+}
+
+impl PathFile {
+    /// Write the `str` to the file like [`write_str`](#method.write_str), but first copy the
+    /// current contents to a backup next to it. Returns the backup.
+    ///
+    /// The new contents are written to a temporary file which is then renamed over the file,
+    /// so it is never left partially written. The file keeps its permissions, and on unix its
+    /// owner and group when the process is allowed to set them (i.e. it runs as root or already
+    /// owns the file).
+    ///
+    /// > Since the file is replaced by a new one, other hard links to it keep the old contents.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{BackupSuffix, PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let tmp = PathDir::new(tmp.path())?;
+    /// let file = PathFile::create(tmp.join("config.toml"))?;
+    /// file.write_str("old")?;
+    ///
+    /// let backup = file.write_with_backup("new", &BackupSuffix::Fixed(".bak".into()))?;
+    /// assert_eq!(tmp.join("config.toml.bak").as_path(), backup.as_path());
+    /// assert_eq!("old", backup.read_string()?);
+    /// assert_eq!("new", file.read_string()?);
+    ///
+    /// let backup = file.write_with_backup("newer", &BackupSuffix::Numbered)?;
+    /// assert_eq!(tmp.join("config.toml.1").as_path(), backup.as_path());
+    /// assert_eq!("new", backup.read_string()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn write_with_backup(&self, s: &str, suffix: &BackupSuffix) -> Result<PathFile> {
+        let backup = suffix.backup(self)?;
+        let meta = self.metadata()?;
+        let tmp = temp_path(self)?;
+        let write = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&tmp)
+            .and_then(|mut f| {
+                f.write_all(s.as_bytes())?;
+                copy_owner(&f, &meta);
+                f.set_permissions(meta.permissions())?;
+                f.sync_all()
+            })
+            .map_err(|err| Error::new(err, "writing", tmp.clone()))
+            .and_then(|_| {
+                fs::rename(&tmp, self).map_err(|err| {
+                    Error::with_dest(err, "renaming", tmp.clone(), self.clone().into())
+                })
+            });
+        if write.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        write.map(|_| backup)
+    }
+
+    /// Rotate the file like a log, keeping `n` old generations of it.
+    ///
+    /// The file is renamed to `.1`, `.1` to `.2`, etc, dropping the generations after `.n`.
+    /// An empty file is then created in its place. See
+    /// [`rotate_with`](#method.rotate_with) for more options.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let tmp = PathDir::new(tmp.path())?;
+    /// let log = PathFile::create(tmp.join("app.log"))?;
+    /// for day in &["monday", "tuesday", "wednesday"] {
+    ///     log.append_str(day)?;
+    ///     log.rotate(2)?;
+    /// }
+    ///
+    /// assert_eq!("", log.read_string()?);
+    /// assert_eq!("wednesday", PathFile::new(tmp.join("app.log.1"))?.read_string()?);
+    /// assert_eq!("tuesday", PathFile::new(tmp.join("app.log.2"))?.read_string()?);
+    /// assert!(!tmp.join("app.log.3").exists());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn rotate(&self, n: usize) -> Result<()> {
+        self.rotate_with(n, &RotateOptions::default())
+    }
+
+    /// Rotate the file like [`rotate`](#method.rotate) with the given options.
+    pub fn rotate_with(&self, n: usize, options: &RotateOptions) -> Result<()> {
+        let generation = |i: usize, gz: bool| {
+            let suffix = if gz {
+                format!(".{}.gz", i)
+            } else {
+                format!(".{}", i)
+            };
+            PathArc::new(with_suffix(self, &suffix))
+        };
+        let permissions = self.metadata()?.permissions();
+
+        // Drop the generations which won't fit anymore, including the ones after a gap.
+        for (i, old) in generations(self)? {
+            if i >= n.max(1) {
+                fs::remove_file(&old).map_err(|err| Error::new(err, "removing", old))?;
+            }
+        }
+
+        for i in (1..n).rev() {
+            // Shift the compressed generation first, so that compressing never replaces it.
+            for &gz in &[true, false] {
+                let from = generation(i, gz);
+                if from.symlink_metadata().is_err() {
+                    continue;
+                }
+                let to_gz = generation(i + 1, true);
+                if options.compress && !gz && to_gz.symlink_metadata().is_err() {
+                    compress(&from, &to_gz)?;
+                } else {
+                    let to = generation(i + 1, gz);
+                    fs::rename(&from, &to)
+                        .map_err(|err| Error::with_dest(err, "renaming", from, to))?;
+                }
+            }
+        }
+
+        if n == 0 {
+            fs::remove_file(self)
+                .map_err(|err| Error::new(err, "removing", self.clone().into()))?;
+        } else {
+            self.clone().rename(generation(1, false))?;
+        }
+        if options.create {
+            let create = fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(self)
+                .and_then(|f| f.set_permissions(permissions));
+            create.map_err(|err| Error::new(err, "creating", self.clone().into()))?;
+        }
+        Ok(())
+    }
+}
+
+/// The existing generations of `file` (both `.N` and `.N.gz`), with their number.
+fn generations(file: &PathFile) -> Result<Vec<(usize, PathArc)>> {
+    let mut out = Vec::new();
+    let dir = match file.parent_dir() {
+        Some(dir) => dir,
+        None => return Ok(out),
+    };
+    for entry in read_dir(&dir)? {
+        let path = entry?.path();
+        let name = match path.file_name() {
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => continue,
+        };
+        let stem = name.strip_suffix(".gz").unwrap_or(&name);
+        let number = match stem.rfind('.') {
+            Some(dot) => &stem[dot + 1..],
+            None => continue,
+        };
+        if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+            continue;
+        }
+        // The rest of the name must be the name of the file itself, which could be anything.
+        let suffix = &name[stem.len() - number.len() - 1..];
+        match number.parse() {
+            Ok(i) if path == with_suffix(file, suffix) => out.push((i, PathArc::from(path))),
+            _ => {}
+        }
+    }
+    Ok(out)
+}
+
+/// Copy `from` to `to` like `fs::copy`, except that `io::ErrorKind::AlreadyExists` is returned
+/// if `to` exists instead of replacing it.
+fn copy_new(from: &PathFile, to: &PathArc) -> Result<PathFile> {
+    let mut out = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(to)
+        .map_err(|err| Error::new(err, "creating", to.clone()))?;
+    let copy = fs::File::open(from).and_then(|mut input| {
+        io::copy(&mut input, &mut out)?;
+        out.set_permissions(input.metadata()?.permissions())
+    });
+    if let Err(err) = copy {
+        let _ = fs::remove_file(to);
+        return Err(Error::with_dest(err, "copying", from.clone().into(), to.clone()));
+    }
+    // The parent of `from` is canonical, and so is the name of a file which was just created.
+    Ok(PathFile::from_abs_unchecked(PathAbs(to.clone())))
+}
+
+/// Give `file` the owner and group from `meta`. This is best effort: only root can give a file
+/// away, so it usually only succeeds if the process already owns `meta`'s file.
+#[cfg(unix)]
+fn copy_owner(file: &fs::File, meta: &fs::Metadata) {
+    use libc;
+    use std::os::unix::fs::MetadataExt;
+    use std::os::unix::io::AsRawFd;
+
+    let _ = unsafe { libc::fchown(file.as_raw_fd(), meta.uid(), meta.gid()) };
+}
+
+#[cfg(not(unix))]
+fn copy_owner(_file: &fs::File, _meta: &fs::Metadata) {}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_os_string();
+    path.push(suffix);
+    path.into()
+}
+
+/// Gzip `src` into `dst` and remove it.
+#[cfg(feature = "compress")]
+fn compress(src: &PathArc, dst: &PathArc) -> Result<()> {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    let write = || -> io::Result<()> {
+        let mut input = fs::File::open(src)?;
+        let out = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(dst)?;
+        let mut encoder = GzEncoder::new(out, Compression::default());
+        io::copy(&mut input, &mut encoder)?;
+        encoder.finish()?.sync_all()
+    };
+    write().map_err(|err| Error::with_dest(err, "compressing", src.clone(), dst.clone()))?;
+    fs::remove_file(src).map_err(|err| Error::new(err, "removing", src.clone()))
+}
+
+#[cfg(not(feature = "compress"))]
+fn compress(src: &PathArc, dst: &PathArc) -> Result<()> {
+    Err(Error::with_dest(
+        io::Error::new(io::ErrorKind::Unsupported, "requires the compress feature"),
+        "compressing",
+        src.clone(),
+        dst.clone(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::super::PathDir;
+    use super::*;
+
+    #[test]
+    fn backup_suffix() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("foo.txt")).unwrap();
+        file.write_str("0").unwrap();
+
+        let backup = file
+            .write_with_backup("1", &BackupSuffix::default())
+            .unwrap();
+        assert_eq!(tmp_abs.join("foo.txt~").as_path(), backup.as_path());
+        let backup = file
+            .write_with_backup("2", &BackupSuffix::default())
+            .unwrap();
+        assert_eq!("1", backup.read_string().unwrap());
+
+        for i in 1..4 {
+            let backup = file
+                .write_with_backup(&(i + 2).to_string(), &BackupSuffix::Numbered)
+                .unwrap();
+            assert_eq!(
+                tmp_abs.join(format!("foo.txt.{}", i)).as_path(),
+                backup.as_path()
+            );
+            assert_eq!((i + 1).to_string(), backup.read_string().unwrap());
+        }
+        assert_eq!("5", file.read_string().unwrap());
+
+        // no temp files are left behind
+        let mut names: Vec<_> = fs::read_dir(&tmp_abs)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        let expected = vec!["foo.txt", "foo.txt.1", "foo.txt.2", "foo.txt.3", "foo.txt~"];
+        assert_eq!(expected, names);
+    }
+
+    #[test]
+    fn rotate_options() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let log = PathFile::create(tmp_abs.join("foo.log")).unwrap();
+
+        // lowering the number of generations drops the extra ones
+        for i in 0..4 {
+            log.write_str(&i.to_string()).unwrap();
+            log.rotate(4).unwrap();
+        }
+        assert!(tmp_abs.join("foo.log.4").exists());
+        log.rotate(2).unwrap();
+        assert_eq!("", read(&tmp_abs, "foo.log.1"));
+        assert_eq!("3", read(&tmp_abs, "foo.log.2"));
+        assert!(!tmp_abs.join("foo.log.3").exists());
+        assert!(!tmp_abs.join("foo.log.4").exists());
+
+        log.write_str("last").unwrap();
+        log.rotate_with(1, RotateOptions::new().create(false))
+            .unwrap();
+        assert!(!log.exists());
+        assert_eq!("last", read(&tmp_abs, "foo.log.1"));
+        assert!(!tmp_abs.join("foo.log.2").exists());
+    }
+
+    #[test]
+    fn backup_never_replaced() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("foo.txt")).unwrap();
+        file.write_str("new").unwrap();
+        let taken = PathFile::create(tmp_abs.join("foo.txt.1")).unwrap();
+        taken.write_str("taken").unwrap();
+
+        let err = copy_new(&file, &PathArc::new(taken.as_path())).unwrap_err();
+        assert_eq!(io::ErrorKind::AlreadyExists, err.io_error().kind());
+        let backup = BackupSuffix::Numbered.backup(&file).unwrap();
+        assert_eq!(tmp_abs.join("foo.txt.2").as_path(), backup.as_path());
+        assert_eq!("new", backup.read_string().unwrap());
+        assert_eq!("taken", taken.read_string().unwrap());
+    }
+
+    #[test]
+    fn rotate_gap() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let log = PathFile::create(tmp_abs.join("foo.log")).unwrap();
+        for name in &["foo.log.1", "foo.log.5", "foo.log.7.gz", "foo.log.x", "foo.logs.9"] {
+            PathFile::create(tmp_abs.join(name)).unwrap();
+        }
+
+        log.rotate(2).unwrap();
+        assert!(tmp_abs.join("foo.log.1").exists());
+        assert!(tmp_abs.join("foo.log.2").exists());
+        assert!(!tmp_abs.join("foo.log.5").exists());
+        assert!(!tmp_abs.join("foo.log.7.gz").exists());
+        assert!(tmp_abs.join("foo.log.x").exists());
+        assert!(tmp_abs.join("foo.logs.9").exists());
+    }
+
+    #[cfg(feature = "compress")]
+    #[test]
+    fn rotate_compress() {
+        use flate2::read::GzDecoder;
+        use std::io::Read;
+
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let log = PathFile::create(tmp_abs.join("foo.log")).unwrap();
+
+        for i in 0..4 {
+            log.write_str(&i.to_string()).unwrap();
+            log.rotate_with(3, RotateOptions::new().compress(true))
+                .unwrap();
+        }
+        assert_eq!("3", read(&tmp_abs, "foo.log.1"));
+        assert!(!tmp_abs.join("foo.log.2").exists());
+        for &(gen, expected) in &[(2, "2"), (3, "1")] {
+            let gz = fs::File::open(tmp_abs.join(format!("foo.log.{}.gz", gen))).unwrap();
+            let mut content = String::new();
+            GzDecoder::new(gz).read_to_string(&mut content).unwrap();
+            assert_eq!(expected, content);
+        }
+        assert!(!tmp_abs.join("foo.log.4.gz").exists());
+    }
+
+    #[cfg(feature = "compress")]
+    #[test]
+    fn rotate_compress_both() {
+        use flate2::read::GzDecoder;
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::io::{Read, Write};
+
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let log = PathFile::create(tmp_abs.join("foo.log")).unwrap();
+        log.write_str("new").unwrap();
+        PathFile::create(tmp_abs.join("foo.log.1"))
+            .unwrap()
+            .write_str("plain")
+            .unwrap();
+        let gz = fs::File::create(tmp_abs.join("foo.log.1.gz")).unwrap();
+        let mut encoder = GzEncoder::new(gz, Compression::default());
+        encoder.write_all(b"compressed").unwrap();
+        encoder.finish().unwrap();
+
+        log.rotate_with(3, RotateOptions::new().compress(true))
+            .unwrap();
+        assert_eq!("new", read(&tmp_abs, "foo.log.1"));
+        assert_eq!("plain", read(&tmp_abs, "foo.log.2"));
+        let gz = fs::File::open(tmp_abs.join("foo.log.2.gz")).unwrap();
+        let mut content = String::new();
+        GzDecoder::new(gz).read_to_string(&mut content).unwrap();
+        assert_eq!("compressed", content);
+    }
+
+    fn read(dir: &PathDir, name: &str) -> String {
+        PathFile::new(dir.join(name))
+            .unwrap()
+            .read_string()
+            .unwrap()
+    }
+}
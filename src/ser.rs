@@ -6,52 +6,14 @@
  * copied, modified, or distributed except according to those terms.
  */
 use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
-use stfu8;
+use std::io;
 
-#[cfg(unix)]
-use std::ffi::OsStr;
-#[cfg(unix)]
-use std::os::unix::ffi::OsStrExt;
-
-#[cfg(windows)]
-use std::ffi::OsString;
-#[cfg(windows)]
-use std::os::windows::ffi::{OsStrExt, OsStringExt};
-
-use super::{PathAbs, PathArc, PathDir, PathFile};
+use super::{Error, ErrorContext, PathAbs, PathArc, PathDir, PathFile, PathOther};
 
 macro_rules! map_err { ($res: expr) => {{
     $res.map_err(|err| serde::de::Error::custom(&err.to_string()))
 }}}
 
-impl PathArc {
-    #[cfg(unix)]
-    pub(crate) fn to_stfu8(&self) -> String {
-        let bytes = self.as_os_str().as_bytes();
-        stfu8::encode_u8(bytes)
-    }
-
-    #[cfg(windows)]
-    pub(crate) fn to_stfu8(&self) -> String {
-        let wide: Vec<u16> = self.as_os_str().encode_wide().collect();
-        stfu8::encode_u16(&wide)
-    }
-
-    #[cfg(unix)]
-    pub(crate) fn from_stfu8(s: &str) -> Result<PathArc, stfu8::DecodeError> {
-        let raw_path = stfu8::decode_u8(s)?;
-        let os_str = OsStr::from_bytes(&raw_path);
-        Ok(PathArc::new(os_str))
-    }
-
-    #[cfg(windows)]
-    pub(crate) fn from_stfu8(s: &str) -> Result<PathArc, stfu8::DecodeError> {
-        let raw_path = stfu8::decode_u16(&s)?;
-        let os_str = OsString::from_wide(&raw_path);
-        Ok(PathArc::new(os_str))
-    }
-}
-
 impl Serialize for PathArc {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -128,9 +90,131 @@ impl<'de> Deserialize<'de> for PathDir {
     }
 }
 
+impl Serialize for PathOther {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let abs: &PathAbs = self.as_ref();
+        abs.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PathOther {
+    fn deserialize<D>(deserializer: D) -> Result<PathOther, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let abs = PathAbs::deserialize(deserializer)?;
+        PathOther::from_abs(abs).map_err(serde::de::Error::custom)
+    }
+}
+
+/// The names used to serialize the kinds of `io::Error`. The names are spelled out rather than
+/// taken from `Debug`, which is not a stable format. Kinds which are not listed (i.e. ones
+/// stabilized after the oldest supported Rust) are serialized as `"Other"`.
+const ERROR_KINDS: &[(io::ErrorKind, &str)] = &[
+    (io::ErrorKind::NotFound, "NotFound"),
+    (io::ErrorKind::PermissionDenied, "PermissionDenied"),
+    (io::ErrorKind::ConnectionRefused, "ConnectionRefused"),
+    (io::ErrorKind::ConnectionReset, "ConnectionReset"),
+    (io::ErrorKind::ConnectionAborted, "ConnectionAborted"),
+    (io::ErrorKind::NotConnected, "NotConnected"),
+    (io::ErrorKind::AddrInUse, "AddrInUse"),
+    (io::ErrorKind::AddrNotAvailable, "AddrNotAvailable"),
+    (io::ErrorKind::BrokenPipe, "BrokenPipe"),
+    (io::ErrorKind::AlreadyExists, "AlreadyExists"),
+    (io::ErrorKind::WouldBlock, "WouldBlock"),
+    (io::ErrorKind::InvalidInput, "InvalidInput"),
+    (io::ErrorKind::InvalidData, "InvalidData"),
+    (io::ErrorKind::TimedOut, "TimedOut"),
+    (io::ErrorKind::WriteZero, "WriteZero"),
+    (io::ErrorKind::Interrupted, "Interrupted"),
+    (io::ErrorKind::Unsupported, "Unsupported"),
+    (io::ErrorKind::UnexpectedEof, "UnexpectedEof"),
+    (io::ErrorKind::OutOfMemory, "OutOfMemory"),
+    (io::ErrorKind::Other, "Other"),
+];
+
+fn kind_name(kind: io::ErrorKind) -> &'static str {
+    ERROR_KINDS
+        .iter()
+        .find(|&&(k, _)| k == kind)
+        .map(|&(_, name)| name)
+        .unwrap_or("Other")
+}
+
+/// The serialized form of `Error`.
+#[derive(Serialize, Deserialize)]
+struct ErrorData {
+    kind: String,
+    os_error: Option<i32>,
+    message: String,
+    action: String,
+    path: PathArc,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dest: Option<PathArc>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    context: Vec<ErrorContext>,
+}
+
+/// Serializes the `io::ErrorKind` (by name, i.e. `"NotFound"`), the raw OS error code, the
+/// message, the action, the path(s) and the contexts.
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let data = ErrorData {
+            kind: kind_name(self.io_err.kind()).to_string(),
+            os_error: self.io_err.raw_os_error(),
+            message: self.io_err.to_string(),
+            action: self.action.clone(),
+            path: self.path.clone(),
+            dest: self.dest.clone(),
+            context: self.context.clone(),
+        };
+        data.serialize(serializer)
+    }
+}
+
+/// Reconstructs the error. If there is a raw OS error code the `io::Error` is created from it,
+/// otherwise from the kind and message.
+///
+/// > OS error codes are platform specific, so an error should only be deserialized on the same
+/// > platform it was serialized on.
+impl<'de> Deserialize<'de> for Error {
+    fn deserialize<D>(deserializer: D) -> Result<Error, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let data = ErrorData::deserialize(deserializer)?;
+        let io_err = match data.os_error {
+            Some(code) => io::Error::from_raw_os_error(code),
+            None => {
+                let kind = ERROR_KINDS
+                    .iter()
+                    .find(|&&(_, name)| name == data.kind)
+                    .map(|&(kind, _)| kind)
+                    .unwrap_or(io::ErrorKind::Other);
+                io::Error::new(kind, data.message)
+            }
+        };
+        Ok(Error {
+            io_err,
+            action: data.action,
+            path: data.path,
+            dest: data.dest,
+            context: data.context,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::super::{PathDir, PathFile, PathType};
+    use std_prelude::*;
+
+    use super::super::{Error, PathArc, PathDir, PathFile, PathType};
 
     #[cfg(unix)]
     static SERIALIZED: &str = "[\
@@ -180,4 +264,48 @@ mod tests {
         let result: Vec<PathType> = serde_json::from_str(&result_str).unwrap();
         assert_eq!(expected, result);
     }
+
+    #[test]
+    fn serde_error() {
+        use serde_json;
+        use std::io;
+        use tempdir::TempDir;
+
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).expect("tmp_abs");
+
+        let missing = tmp_abs.join("missing.txt");
+        let err = PathFile::new(&missing).unwrap_err();
+        let value = serde_json::to_value(&err).unwrap();
+        assert_eq!("NotFound", value["kind"]);
+        assert_eq!(err.io_error().raw_os_error(), value["os_error"].as_i64().map(|c| c as i32));
+        assert_eq!(err.action(), value["action"]);
+        assert_eq!(PathArc::new(&missing).to_stfu8(), value["path"]);
+        assert!(value.get("dest").is_none());
+
+        let result: Error = serde_json::from_value(value).unwrap();
+        assert_eq!(err.to_string(), result.to_string());
+        assert_eq!(io::ErrorKind::NotFound, result.io_error().kind());
+
+        // custom errors with a destination
+        let err = Error::with_dest(
+            io::Error::new(io::ErrorKind::InvalidData, "bad data"),
+            "copying",
+            PathArc::new("/foo"),
+            PathArc::new("/bar"),
+        )
+        .context("copying tree", "/");
+        let result: Error = serde_json::from_str(&serde_json::to_string(&err).unwrap()).unwrap();
+        assert_eq!(err.to_string(), result.to_string());
+        assert_eq!(io::ErrorKind::InvalidData, result.io_error().kind());
+        assert_eq!(Some(Path::new("/bar")), result.dest_path());
+        assert_eq!(err.contexts(), result.contexts());
+
+        // kinds which are not known are deserialized as `Other`
+        let mut value = serde_json::to_value(&err).unwrap();
+        value["kind"] = "SomeFutureKind".into();
+        let result: Error = serde_json::from_value(value).unwrap();
+        assert_eq!(io::ErrorKind::Other, result.io_error().kind());
+        assert_eq!("bad data", result.io_error().to_string());
+    }
 }
@@ -0,0 +1,304 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Statistics about the filesystem containing a directory.
+
+use std::io;
+use std_prelude::*;
+
+use super::{Error, Result};
+use super::PathDir;
+
+/// Statistics about a mounted filesystem, returned by
+/// [`PathDir::fs_stats`](struct.PathDir.html#method.fs_stats).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FsStats {
+    total: u64,
+    free: u64,
+    available: u64,
+    inodes: u64,
+    inodes_free: u64,
+    mount_point: PathDir,
+    fs_type: Option<String>,
+    read_only: bool,
+}
+
+impl FsStats {
+    /// The total size of the filesystem, in bytes.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// The number of free bytes in the filesystem.
+    pub fn free(&self) -> u64 {
+        self.free
+    }
+
+    /// The number of free bytes available to unprivileged users. This is what should be checked
+    /// before writing large files.
+    pub fn available(&self) -> u64 {
+        self.available
+    }
+
+    /// The total number of inodes (i.e. the maximum number of files) of the filesystem.
+    pub fn inodes(&self) -> u64 {
+        self.inodes
+    }
+
+    /// The number of free inodes.
+    pub fn inodes_free(&self) -> u64 {
+        self.inodes_free
+    }
+
+    /// The directory where the filesystem is mounted.
+    ///
+    /// > On linux this is looked up in `/proc/self/mounts`, so bind mounts and btrfs subvolumes
+    /// > are reported correctly. Elsewhere it is the top-most ancestor on the same device.
+    pub fn mount_point(&self) -> &PathDir {
+        &self.mount_point
+    }
+
+    /// The type of the filesystem (i.e. `ext4`, `tmpfs`), if it could be determined.
+    ///
+    /// > This is currently only determined on linux, through `/proc/self/mounts`.
+    pub fn fs_type(&self) -> Option<&str> {
+        self.fs_type.as_deref()
+    }
+
+    /// Whether the filesystem is mounted read-only.
+    pub fn read_only(&self) -> bool {
+        self.read_only
+    }
+}
+
+impl PathDir {
+    /// Get statistics about the filesystem containing the directory, such as the free space.
+    ///
+    /// > This is implemented with `statvfs` and is not yet supported on windows, where
+    /// > `io::ErrorKind::Unsupported` is returned.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// use path_abs::PathDir;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let src = PathDir::new("src")?;
+    /// let stats = src.fs_stats()?;
+    /// assert!(stats.available() <= stats.total());
+    /// assert!(src.starts_with(stats.mount_point()));
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn fs_stats(&self) -> Result<FsStats> {
+        let vfs = statvfs(self.as_path())
+            .map_err(|err| Error::new(err, "getting filesystem stats of", self.clone().into()))?;
+        let (mount_point, fs_type) = match find_mount(self) {
+            Some((mount_point, fs_type)) => (mount_point, Some(fs_type)),
+            None => (self.mount_point()?, None),
+        };
+        Ok(FsStats {
+            total: vfs.blocks * vfs.frsize,
+            free: vfs.bfree * vfs.frsize,
+            available: vfs.bavail * vfs.frsize,
+            inodes: vfs.files,
+            inodes_free: vfs.ffree,
+            mount_point,
+            fs_type,
+            read_only: vfs.read_only,
+        })
+    }
+
+    /// Find the mount point of the directory: the top-most ancestor on the same device.
+    ///
+    /// This is only a fallback for when the mounts can't be listed, since the mount point of a
+    /// bind mount or a btrfs subvolume is on the same device as its parent.
+    #[cfg(unix)]
+    fn mount_point(&self) -> Result<PathDir> {
+        use std::os::unix::fs::MetadataExt;
+
+        let dev = self.metadata()?.dev();
+        let mut mount_point = self.clone();
+        while let Some(parent) = mount_point.parent_dir() {
+            if parent.metadata()?.dev() != dev {
+                break;
+            }
+            mount_point = parent;
+        }
+        Ok(mount_point)
+    }
+
+    #[cfg(not(unix))]
+    fn mount_point(&self) -> Result<PathDir> {
+        Err(unsupported(self))
+    }
+}
+
+struct StatVfs {
+    frsize: u64,
+    blocks: u64,
+    bfree: u64,
+    bavail: u64,
+    files: u64,
+    ffree: u64,
+    read_only: bool,
+}
+
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)]
+fn statvfs(path: &Path) -> io::Result<StatVfs> {
+    use std::ffi::CString;
+    use std::mem;
+    use std::os::unix::ffi::OsStrExt;
+    use libc;
+
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    let mut vfs: libc::statvfs = unsafe { mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut vfs) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(StatVfs {
+        frsize: vfs.f_frsize as u64,
+        blocks: vfs.f_blocks as u64,
+        bfree: vfs.f_bfree as u64,
+        bavail: vfs.f_bavail as u64,
+        files: vfs.f_files as u64,
+        ffree: vfs.f_ffree as u64,
+        read_only: vfs.f_flag as u64 & libc::ST_RDONLY as u64 != 0,
+    })
+}
+
+#[cfg(not(unix))]
+fn statvfs(_path: &Path) -> io::Result<StatVfs> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "filesystem stats are not supported"))
+}
+
+#[cfg(not(unix))]
+fn unsupported(dir: &PathDir) -> Error {
+    Error::new(
+        io::Error::new(io::ErrorKind::Unsupported, "filesystem stats are not supported"),
+        "getting filesystem stats of",
+        dir.clone().into(),
+    )
+}
+
+/// Find the mount point of `dir` and the type of its filesystem in `/proc/self/mounts`.
+#[cfg(target_os = "linux")]
+fn find_mount(dir: &PathDir) -> Option<(PathDir, String)> {
+    use std::fs;
+
+    let mounts = fs::read_to_string("/proc/self/mounts").ok()?;
+    let (mount_point, fs_type) = parse_mounts(&mounts, dir)?;
+    Some((PathDir::new(mount_point).ok()?, fs_type))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn find_mount(_dir: &PathDir) -> Option<(PathDir, String)> {
+    None
+}
+
+/// Find the mount containing `dir` in the contents of `/proc/self/mounts`: the one whose
+/// directory is the longest prefix of `dir`.
+#[cfg(target_os = "linux")]
+fn parse_mounts(mounts: &str, dir: &Path) -> Option<(PathBuf, String)> {
+    let mut found: Option<(PathBuf, &str)> = None;
+    for line in mounts.lines() {
+        let mut fields = line.split(' ');
+        let (mount_point, fs_type) = match (fields.nth(1), fields.next()) {
+            (Some(mount_point), Some(fs_type)) => (unescape_mount(mount_point), fs_type),
+            _ => continue,
+        };
+        let mount_point = PathBuf::from(mount_point);
+        if !dir.starts_with(&mount_point) {
+            continue;
+        }
+        // Mounts can shadow each other, the last one at the same directory is the visible one.
+        let longest = match found {
+            Some((ref prev, _)) => mount_point.components().count() >= prev.components().count(),
+            None => true,
+        };
+        if longest {
+            found = Some((mount_point, fs_type));
+        }
+    }
+    found.map(|(mount_point, fs_type)| (mount_point, fs_type.to_string()))
+}
+
+/// Undo the octal escaping (i.e. `\040` for a space) used in `/proc/self/mounts`.
+#[cfg(target_os = "linux")]
+fn unescape_mount(field: &str) -> String {
+    let mut out = Vec::with_capacity(field.len());
+    let mut i = 0;
+    while i < field.len() {
+        let escaped = if field.as_bytes()[i] == b'\\' {
+            field.get(i + 1..i + 4).and_then(|s| u8::from_str_radix(s, 8).ok())
+        } else {
+            None
+        };
+        match escaped {
+            Some(b) => {
+                out.push(b);
+                i += 4;
+            }
+            None => {
+                out.push(field.as_bytes()[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::PathDir;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn unescape_mount() {
+        assert_eq!("/mnt/my disk", super::unescape_mount("/mnt/my\\040disk"));
+        assert_eq!("/mnt/a\\b", super::unescape_mount("/mnt/a\\b"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parse_mounts() {
+        use std::path::{Path, PathBuf};
+
+        let mounts = "\
+            /dev/sda1 / ext4 rw,relatime 0 0\n\
+            /dev/sda2 /home btrfs rw,subvol=/home 0 0\n\
+            /dev/sda2 /srv/data btrfs rw,subvol=/data 0 0\n\
+            tmpfs /srv/data/cache tmpfs rw 0 0\n\
+            /dev/sda1 /srv/data/cache ext4 rw 0 0\n";
+        let find = |dir: &str| super::parse_mounts(mounts, Path::new(dir)).unwrap();
+
+        assert_eq!((PathBuf::from("/"), "ext4".to_string()), find("/usr/lib"));
+        assert_eq!((PathBuf::from("/home"), "btrfs".to_string()), find("/home/user"));
+        assert_eq!((PathBuf::from("/srv/data"), "btrfs".to_string()), find("/srv/data"));
+        // a component must match entirely
+        assert_eq!((PathBuf::from("/"), "ext4".to_string()), find("/homes"));
+        // the bind mount shadows the tmpfs
+        assert_eq!(
+            (PathBuf::from("/srv/data/cache"), "ext4".to_string()),
+            find("/srv/data/cache/x")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sanity_fs_stats() {
+        let root = PathDir::new("/").unwrap();
+        let stats = root.fs_stats().unwrap();
+        assert_eq!(&root, stats.mount_point());
+        assert!(stats.free() <= stats.total());
+        assert!(stats.inodes_free() <= stats.inodes());
+        if cfg!(target_os = "linux") {
+            assert!(stats.fs_type().is_some());
+        }
+    }
+}
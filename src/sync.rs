@@ -0,0 +1,608 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! One-way synchronization of directory trees.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::sync::Arc;
+use std_prelude::*;
+
+use filetime::{self, FileTime};
+
+use super::{Error, Result};
+use super::{DiffEntry, DiffMode, PathArc, PathDir, PathFile};
+use dir::symlink_dir;
+use utils::{read_dir, temp_path};
+
+type Filter = Arc<dyn Fn(&Path) -> bool + Send + Sync>;
+
+/// Options and flags which can be used to configure how
+/// [`PathDir::sync_to`](struct.PathDir.html#method.sync_to) synchronizes a directory.
+///
+/// This builder works like `std::fs::OpenOptions`: create it with `new` then chain calls to the
+/// methods to set each option.
+#[derive(Clone)]
+pub struct SyncOptions {
+    mode: DiffMode,
+    delete: bool,
+    dry_run: bool,
+    include: Vec<Filter>,
+    exclude: Vec<Filter>,
+}
+
+impl SyncOptions {
+    /// Create a blank set of options.
+    ///
+    /// By default files are compared with `DiffMode::MtimeSize`, extraneous entries in the
+    /// destination are kept, the changes are actually performed and every path is included.
+    pub fn new() -> SyncOptions {
+        SyncOptions {
+            mode: DiffMode::MtimeSize,
+            delete: false,
+            dry_run: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+        }
+    }
+
+    /// Set how files are compared to decide whether they need to be copied.
+    pub fn mode(&mut self, mode: DiffMode) -> &mut SyncOptions {
+        self.mode = mode;
+        self
+    }
+
+    /// Set whether entries of the destination that do not exist in the source are removed.
+    pub fn delete(&mut self, delete: bool) -> &mut SyncOptions {
+        self.delete = delete;
+        self
+    }
+
+    /// Set whether to only report the actions instead of performing them.
+    pub fn dry_run(&mut self, dry_run: bool) -> &mut SyncOptions {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Only synchronize files for which `filter` returns true. If called multiple times a file
+    /// is synchronized if any of the filters matches.
+    ///
+    /// The filter is given the path relative to the directories being synchronized. Directories
+    /// are not passed to include filters, they are always traversed. With `delete` only the
+    /// included files of an extraneous directory are removed, and the directory itself is only
+    /// removed if nothing else is left in it.
+    pub fn include<F>(&mut self, filter: F) -> &mut SyncOptions
+    where
+        F: Fn(&Path) -> bool + Send + Sync + 'static,
+    {
+        self.include.push(Arc::new(filter));
+        self
+    }
+
+    /// Never synchronize (nor delete) the entries for which `filter` returns true. Excluding a
+    /// directory excludes everything inside of it.
+    ///
+    /// The filter is given the path relative to the directories being synchronized.
+    pub fn exclude<F>(&mut self, filter: F) -> &mut SyncOptions
+    where
+        F: Fn(&Path) -> bool + Send + Sync + 'static,
+    {
+        self.exclude.push(Arc::new(filter));
+        self
+    }
+
+    fn is_included(&self, path: &Path, is_dir: bool) -> bool {
+        // Excluding a directory excludes everything inside of it, even when it is only
+        // traversed because it exists on both sides.
+        let excluded = path
+            .ancestors()
+            .filter(|p| !p.as_os_str().is_empty())
+            .any(|p| self.exclude.iter().any(|f| f(p)));
+        if excluded {
+            return false;
+        }
+        is_dir || self.include.is_empty() || self.include.iter().any(|f| f(path))
+    }
+}
+
+impl Default for SyncOptions {
+    fn default() -> SyncOptions {
+        SyncOptions::new()
+    }
+}
+
+impl fmt::Debug for SyncOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SyncOptions")
+            .field("delete", &self.delete)
+            .field("dry_run", &self.dry_run)
+            .field("include", &self.include.len())
+            .field("exclude", &self.exclude.len())
+            .finish()
+    }
+}
+
+/// An action performed (or, for a dry run, that would be performed) by
+/// [`PathDir::sync_to`](struct.PathDir.html#method.sync_to).
+///
+/// Paths are relative to the directories being synchronized.
+#[derive(Debug, Clone, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub enum SyncAction {
+    /// A directory was created in the destination.
+    CreateDir(PathBuf),
+    /// A file was copied from the source to the destination.
+    Copy(PathBuf),
+    /// A symlink to a directory was recreated in the destination, pointing to the same target
+    /// as in the source.
+    Link(PathBuf),
+    /// A file or directory was removed from the destination.
+    Remove(PathBuf),
+}
+
+impl PathDir {
+    /// Synchronize `dst` with `self`, copying only the files which are new or changed.
+    ///
+    /// Returns the list of actions that were performed, in the order they were performed. With
+    /// `SyncOptions::dry_run` nothing is changed and the actions that _would_ have been
+    /// performed are returned.
+    ///
+    /// Copied files keep the modification time of the source, so a later sync using
+    /// `DiffMode::MtimeSize` will not copy them again.
+    ///
+    /// Symlinks to files are copied as regular files. Symlinks to directories are never
+    /// descended into, they are recreated in the destination with the same target instead.
+    /// Special files and broken symlinks in the source are skipped.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::path::PathBuf;
+    /// use path_abs::{PathDir, PathFile, SyncAction, SyncOptions};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let tmp = PathDir::new(tmp.path())?;
+    /// let assets = PathDir::create(tmp.join("assets"))?;
+    /// let release = PathDir::create(tmp.join("release"))?;
+    /// PathFile::create(assets.join("logo.png"))?;
+    /// PathFile::create(release.join("old.png"))?;
+    ///
+    /// let mut options = SyncOptions::new();
+    /// options.delete(true);
+    ///
+    /// let actions = assets.sync_to(&release, &options)?;
+    /// assert_eq!(actions, vec![
+    ///     SyncAction::Copy(PathBuf::from("logo.png")),
+    ///     SyncAction::Remove(PathBuf::from("old.png")),
+    /// ]);
+    ///
+    /// // Nothing changed, so there is nothing to do.
+    /// assert_eq!(assets.sync_to(&release, &options)?, vec![]);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn sync_to(&self, dst: &PathDir, options: &SyncOptions) -> Result<Vec<SyncAction>> {
+        let mut syncer = Syncer {
+            src: self,
+            dst,
+            options,
+            actions: Vec::new(),
+        };
+        let synced = dst
+            .diff(self, options.mode)
+            .and_then(|entries| entries.into_iter().try_for_each(|entry| syncer.apply(entry)));
+        synced.map_err(|err| err.context_dest("synchronizing", self, dst))?;
+        Ok(syncer.actions)
+    }
+}
+
+struct Syncer<'a> {
+    src: &'a PathDir,
+    dst: &'a PathDir,
+    options: &'a SyncOptions,
+    actions: Vec<SyncAction>,
+}
+
+impl<'a> Syncer<'a> {
+    fn apply(&mut self, entry: DiffEntry) -> Result<()> {
+        match entry {
+            DiffEntry::Modified { path, .. } => {
+                if self.options.is_included(&path, false) {
+                    self.copy(path)?;
+                }
+                Ok(())
+            }
+            // The path is missing on one side or is something else on each side, so look at
+            // what is actually there.
+            entry => self.reconcile(entry.path().to_path_buf()),
+        }
+    }
+
+    /// Make `path` in `dst` match `path` in `src`, where at most one of them is a directory.
+    fn reconcile(&mut self, path: PathBuf) -> Result<()> {
+        let kind = kind_of(&self.src.join(&path))?;
+        let exists = kind_of(&self.dst.join(&path))? != Kind::Missing;
+        if self.is_synced(&path, kind) {
+            if exists {
+                self.remove(path.clone())?;
+            }
+            self.add(path, kind)?;
+        } else if exists && self.options.delete {
+            // Nothing replaces it, so it is extraneous.
+            self.remove_extraneous(path)?;
+        }
+        Ok(())
+    }
+
+    /// Whether `add` would add anything for the `kind` of `path` in `src`.
+    fn is_synced(&self, path: &Path, kind: Kind) -> bool {
+        match kind {
+            // A symlink is included like a file.
+            Kind::File | Kind::DirLink => self.options.is_included(path, false),
+            Kind::Dir => self.options.is_included(path, true),
+            Kind::Missing | Kind::Skipped => false,
+        }
+    }
+
+    /// Add `path` (which is a `kind` in `src`) to `dst`, recursively.
+    fn add(&mut self, path: PathBuf, kind: Kind) -> Result<()> {
+        if !self.is_synced(&path, kind) {
+            return Ok(());
+        }
+        match kind {
+            Kind::File => self.copy(path)?,
+            Kind::DirLink => self.link(path)?,
+            Kind::Dir => {
+                self.create_dir(path.clone())?;
+                for name in list_names(&self.src.join(&path))? {
+                    let child = path.join(name);
+                    let kind = kind_of(&self.src.join(&child))?;
+                    self.add(child, kind)?;
+                }
+            }
+            Kind::Missing | Kind::Skipped => {}
+        }
+        Ok(())
+    }
+
+    /// Remove `path` (which only exists in `dst`) if it is included. With include filters only
+    /// the included files of a directory are removed, so this recurses and only removes the
+    /// directory if it ends up empty.
+    ///
+    /// Returns whether the entry was removed.
+    fn remove_extraneous(&mut self, path: PathBuf) -> Result<bool> {
+        // A symlink is removed like a file, never following it.
+        let to = self.dst.join(&path);
+        let is_dir = kind_of(&to)? == Kind::Dir;
+        if !self.options.is_included(&path, is_dir) {
+            return Ok(false);
+        }
+        if is_dir && !self.options.include.is_empty() {
+            let mut empty = true;
+            for name in list_names(&to)? {
+                empty &= self.remove_extraneous(path.join(name))?;
+            }
+            if !empty {
+                return Ok(false);
+            }
+        }
+        self.remove(path)?;
+        Ok(true)
+    }
+
+    fn create_dir(&mut self, path: PathBuf) -> Result<()> {
+        if !self.options.dry_run {
+            let to = self.dst.join(&path);
+            fs::create_dir(&to).map_err(|err| Error::new(err, "creating", to))?;
+        }
+        self.actions.push(SyncAction::CreateDir(path));
+        Ok(())
+    }
+
+    fn link(&mut self, path: PathBuf) -> Result<()> {
+        if !self.options.dry_run {
+            let from = self.src.join(&path);
+            let target = fs::read_link(&from)
+                .map_err(|err| Error::new(err, "reading link", PathArc::new(&from)))?;
+            let to = self.dst.join(&path);
+            symlink_dir(&target, &to)
+                .map_err(|err| Error::with_dest(err, "symlinking", PathArc::new(target), to))?;
+        }
+        self.actions.push(SyncAction::Link(path));
+        Ok(())
+    }
+
+    fn copy(&mut self, path: PathBuf) -> Result<()> {
+        if !self.options.dry_run {
+            let from = PathFile::new(self.src.join(&path))?;
+            // Copy to a new file next to the destination and rename it into place. This
+            // replaces a symlink at the destination instead of writing through it, which could
+            // write outside of `dst`.
+            let to = PathArc::new(self.dst.join(&path));
+            let tmp = temp_path(&to)?;
+            let copied = copy_new(&from, &tmp).and_then(|_| {
+                fs::rename(&tmp, &to)
+                    .map_err(|err| Error::with_dest(err, "renaming", tmp.clone(), to.clone()))
+            });
+            if copied.is_err() {
+                let _ = fs::remove_file(&tmp);
+            }
+            copied?;
+        }
+        self.actions.push(SyncAction::Copy(path));
+        Ok(())
+    }
+
+    fn remove(&mut self, path: PathBuf) -> Result<()> {
+        if !self.options.dry_run {
+            // Never resolve symlinks here: removing the target of a link could delete data
+            // outside of `dst`.
+            let to = self.dst.join(&path);
+            let is_dir = to.symlink_metadata()?.file_type().is_dir();
+            let removed = if is_dir {
+                fs::remove_dir_all(&to)
+            } else {
+                fs::remove_file(&to)
+            };
+            removed.map_err(|err| Error::new(err, "removing", to))?;
+        }
+        self.actions.push(SyncAction::Remove(path));
+        Ok(())
+    }
+}
+
+/// Copy `from` to the new file `to`, keeping its permissions and modification time.
+fn copy_new(from: &PathFile, to: &PathArc) -> Result<()> {
+    let meta = from.metadata()?;
+    let modified = meta
+        .modified()
+        .map_err(|err| Error::new(err, "getting modified time of", from.clone().into()))?;
+    let mut read = from.read()?;
+    let copy = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(to)
+        .and_then(|mut f| {
+            io::copy(&mut read.0.file, &mut f)?;
+            f.set_permissions(meta.permissions())
+        })
+        .and_then(|_| filetime::set_file_mtime(to, FileTime::from_system_time(modified)));
+    copy.map_err(|err| Error::with_dest(err, "copying", from.clone().into(), to.clone()))
+}
+
+/// What an entry is, as far as synchronizing it is concerned.
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Missing,
+    /// A file, or a symlink to a file.
+    File,
+    Dir,
+    /// A symlink to a directory, which is recreated instead of descended into.
+    DirLink,
+    /// A special file or a broken symlink.
+    Skipped,
+}
+
+fn kind_of(path: &Path) -> Result<Kind> {
+    let meta = match path.symlink_metadata() {
+        Ok(meta) => meta,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(Kind::Missing),
+        Err(err) => return Err(Error::new(err, "getting metadata of", PathArc::new(path))),
+    };
+    let ty = meta.file_type();
+    Ok(if ty.is_symlink() {
+        match path.metadata() {
+            Ok(ref meta) if meta.is_dir() => Kind::DirLink,
+            Ok(ref meta) if meta.is_file() => Kind::File,
+            _ => Kind::Skipped,
+        }
+    } else if ty.is_dir() {
+        Kind::Dir
+    } else if ty.is_file() {
+        Kind::File
+    } else {
+        Kind::Skipped
+    })
+}
+
+/// The names of the entries of `dir`, sorted.
+fn list_names(dir: &Path) -> Result<BTreeSet<OsString>> {
+    read_dir(&PathArc::new(dir))?
+        .map(|entry| entry.map(|e| e.file_name()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use tempdir::TempDir;
+
+    use super::super::{DiffMode, PathDir, PathFile};
+    use super::*;
+
+    #[test]
+    fn sanity_sync() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let src = PathDir::create(tmp_abs.join("src")).unwrap();
+        let dst = PathDir::create(tmp_abs.join("dst")).unwrap();
+
+        let sub = PathDir::create(src.join("sub")).unwrap();
+        PathFile::create(sub.join("foo.txt")).unwrap().write_str("foo").unwrap();
+        PathFile::create(sub.join("foo.tmp")).unwrap();
+        PathFile::create(src.join("bar.txt")).unwrap().write_str("bar").unwrap();
+        PathFile::create(dst.join("bar.txt")).unwrap().write_str("old").unwrap();
+        PathFile::create(dst.join("extra.txt")).unwrap();
+        PathFile::create(dst.join("keep.tmp")).unwrap();
+
+        let mut options = SyncOptions::new();
+        options
+            .mode(DiffMode::Content)
+            .delete(true)
+            .dry_run(true)
+            .exclude(|p| p.extension().map(|e| e == "tmp").unwrap_or(false));
+
+        let expected = vec![
+            SyncAction::Copy(PathBuf::from("bar.txt")),
+            SyncAction::Remove(PathBuf::from("extra.txt")),
+            SyncAction::CreateDir(PathBuf::from("sub")),
+            SyncAction::Copy(PathBuf::from("sub").join("foo.txt")),
+        ];
+
+        // a dry run does not change anything
+        assert_eq!(expected, src.sync_to(&dst, &options).unwrap());
+        assert_eq!("old", PathFile::new(dst.join("bar.txt")).unwrap().read_string().unwrap());
+
+        options.dry_run(false);
+        assert_eq!(expected, src.sync_to(&dst, &options).unwrap());
+        assert_eq!("bar", PathFile::new(dst.join("bar.txt")).unwrap().read_string().unwrap());
+        assert_eq!(
+            "foo",
+            PathFile::new(dst.join("sub").join("foo.txt")).unwrap().read_string().unwrap()
+        );
+        assert!(!dst.join("extra.txt").exists());
+        assert!(!dst.join("sub").join("foo.tmp").exists());
+        assert!(dst.join("keep.tmp").exists());
+
+        // mtimes are preserved, so the cheap mode finds nothing to do
+        options.mode(DiffMode::MtimeSize);
+        assert_eq!(Vec::<SyncAction>::new(), src.sync_to(&dst, &options).unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sync_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let src = PathDir::create(tmp_abs.join("src")).unwrap();
+        let dst = PathDir::create(tmp_abs.join("dst")).unwrap();
+
+        // a loop is not descended into, even when added with its parent
+        let sub = PathDir::create(src.join("sub")).unwrap();
+        symlink(".", sub.join("loop")).unwrap();
+        symlink("missing", src.join("broken")).unwrap();
+        PathFile::create(dst.join("broken")).unwrap();
+
+        let mut options = SyncOptions::new();
+        options.delete(true);
+        let expected = vec![
+            SyncAction::Remove(PathBuf::from("broken")),
+            SyncAction::CreateDir(PathBuf::from("sub")),
+            SyncAction::Link(PathBuf::from("sub").join("loop")),
+        ];
+        assert_eq!(expected, src.sync_to(&dst, &options).unwrap());
+        assert!(!dst.join("broken").exists());
+        assert!(dst.join("sub").exists());
+        assert_eq!(Path::new("."), fs::read_link(dst.join("sub").join("loop")).unwrap());
+        assert_eq!(Vec::<SyncAction>::new(), src.sync_to(&dst, &options).unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sync_dir_symlink() {
+        use std::os::unix::fs::symlink;
+
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let src = PathDir::create(tmp_abs.join("src")).unwrap();
+        let dst = PathDir::create(tmp_abs.join("dst")).unwrap();
+        for dir in &[&src, &dst] {
+            PathDir::create(dir.join("sub")).unwrap();
+            PathDir::create(dir.join("other")).unwrap();
+            symlink("sub", dir.join("link")).unwrap();
+        }
+
+        let mut options = SyncOptions::new();
+        options.delete(true);
+        assert_eq!(Vec::<SyncAction>::new(), src.sync_to(&dst, &options).unwrap());
+        assert_eq!(Path::new("sub"), fs::read_link(dst.join("link")).unwrap());
+
+        // a retargeted link is replaced
+        fs::remove_file(src.join("link")).unwrap();
+        symlink("other", src.join("link")).unwrap();
+        let expected = vec![
+            SyncAction::Remove(PathBuf::from("link")),
+            SyncAction::Link(PathBuf::from("link")),
+        ];
+        assert_eq!(expected, src.sync_to(&dst, &options).unwrap());
+        assert_eq!(Path::new("other"), fs::read_link(dst.join("link")).unwrap());
+        assert!(dst.join("sub").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sync_replaces_dst_symlink() {
+        use std::os::unix::fs::symlink;
+
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let src = PathDir::create(tmp_abs.join("src")).unwrap();
+        let dst = PathDir::create(tmp_abs.join("dst")).unwrap();
+        let outside = PathFile::create(tmp_abs.join("outside")).unwrap();
+        outside.write_str("outside").unwrap();
+
+        PathFile::create(src.join("foo")).unwrap().write_str("foo").unwrap();
+        symlink(&outside, dst.join("foo")).unwrap();
+
+        let expected = vec![SyncAction::Copy(PathBuf::from("foo"))];
+        assert_eq!(expected, src.sync_to(&dst, &SyncOptions::new()).unwrap());
+        assert!(!fs::symlink_metadata(dst.join("foo")).unwrap().file_type().is_symlink());
+        assert_eq!("foo", PathFile::new(dst.join("foo")).unwrap().read_string().unwrap());
+        assert_eq!("outside", outside.read_string().unwrap());
+    }
+
+    #[test]
+    fn sync_delete_included() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let src = PathDir::create(tmp_abs.join("src")).unwrap();
+        let dst = PathDir::create(tmp_abs.join("dst")).unwrap();
+
+        let extra = PathDir::create(dst.join("extra")).unwrap();
+        PathFile::create(extra.join("foo.txt")).unwrap();
+        PathFile::create(extra.join("keep.rs")).unwrap();
+        let only_txt = PathDir::create(dst.join("only_txt")).unwrap();
+        PathFile::create(only_txt.join("bar.txt")).unwrap();
+
+        let mut options = SyncOptions::new();
+        options
+            .delete(true)
+            .include(|p| p.extension().map(|e| e == "txt").unwrap_or(false));
+        let expected = vec![
+            SyncAction::Remove(PathBuf::from("extra").join("foo.txt")),
+            SyncAction::Remove(PathBuf::from("only_txt").join("bar.txt")),
+            SyncAction::Remove(PathBuf::from("only_txt")),
+        ];
+        assert_eq!(expected, src.sync_to(&dst, &options).unwrap());
+        assert!(extra.join("keep.rs").exists());
+        assert!(!extra.join("foo.txt").exists());
+        assert!(!only_txt.exists());
+    }
+
+    #[test]
+    fn sync_exclude_common_dir() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let src = PathDir::create(tmp_abs.join("src")).unwrap();
+        let dst = PathDir::create(tmp_abs.join("dst")).unwrap();
+
+        let src_cache = PathDir::create(src.join("cache")).unwrap();
+        PathFile::create(src_cache.join("new.bin")).unwrap();
+        let dst_cache = PathDir::create(dst.join("cache")).unwrap();
+        PathFile::create(dst_cache.join("precious.bin")).unwrap();
+
+        let mut options = SyncOptions::new();
+        options.delete(true).exclude(|p| p == Path::new("cache"));
+        assert_eq!(Vec::<SyncAction>::new(), src.sync_to(&dst, &options).unwrap());
+        assert!(dst_cache.join("precious.bin").exists());
+        assert!(!dst_cache.join("new.bin").exists());
+    }
+}
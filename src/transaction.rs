@@ -0,0 +1,502 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Applying changes to multiple files all at once.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::io;
+use std_prelude::*;
+
+use super::{Error, Result};
+use super::{PathArc, PathDir};
+use utils::{os_str_bytes, os_string_from_bytes};
+
+/// The name of the directory holding the journal and the staged files.
+const JOURNAL_DIR: &str = ".path_abs.transaction";
+/// The name of the journal file inside of `JOURNAL_DIR`.
+const JOURNAL: &str = "journal";
+
+/// Journal record of a rename which is about to be performed.
+const RECORD_RENAME: u8 = b'r';
+/// Journal record marking that every rename was performed.
+const RECORD_COMMIT: u8 = b'c';
+
+/// A set of changes to files inside of a directory which are applied all together or not at
+/// all, created with [`PathDir::transaction`](struct.PathDir.html#method.transaction).
+///
+/// Writes are staged in a journal directory (`.path_abs.transaction`) as soon as they are added.
+/// On [`commit`](#method.commit) every change is applied with atomic renames, each of which is
+/// first recorded in the journal. If any of them fails the ones already performed are undone.
+///
+/// If the transaction is dropped without being committed then nothing is changed. If the
+/// process crashes during a commit, the journal is left behind and
+/// [`PathDir::recover_transaction`](struct.PathDir.html#method.recover_transaction) restores the
+/// directory to its state before the commit (or finishes it, if it got far enough).
+///
+/// > Files can be anywhere inside of the directory, but must be on the same filesystem for the
+/// > renames to be atomic. Only one transaction can be in progress per directory.
+///
+/// # Examples
+/// ```rust
+/// # extern crate path_abs;
+/// # extern crate tempdir;
+/// use path_abs::{PathDir, PathFile};
+///
+/// # fn try_main() -> ::std::io::Result<()> {
+/// # let tmp = tempdir::TempDir::new("ex")?;
+/// # let tmp = PathDir::new(tmp.path())?;
+/// let config = PathDir::create(tmp.join("config"))?;
+/// PathFile::create(config.join("server.toml"))?.write_str("port = 80")?;
+/// PathFile::create(config.join("old.toml"))?;
+///
+/// let mut tx = config.transaction()?;
+/// tx.write("server.toml", b"port = 8080")?;
+/// tx.write("client.toml", b"port = 8080")?;
+/// tx.remove("old.toml");
+/// tx.commit()?;
+///
+/// assert_eq!("port = 8080", PathFile::new(config.join("server.toml"))?.read_string()?);
+/// assert_eq!("port = 8080", PathFile::new(config.join("client.toml"))?.read_string()?);
+/// assert!(!config.join("old.toml").exists());
+/// # Ok(()) } fn main() { try_main().unwrap() }
+/// ```
+pub struct Transaction {
+    dir: PathDir,
+    journal_dir: PathArc,
+    journal: fs::File,
+    ops: Vec<Op>,
+    done: bool,
+}
+
+enum Op {
+    Write { path: PathArc, staged: PathArc },
+    Remove { path: PathArc },
+    Rename { from: PathArc, to: PathArc },
+}
+
+impl PathDir {
+    /// Start a [`Transaction`](struct.Transaction.html) on files inside of the directory.
+    ///
+    /// Returns `io::ErrorKind::AlreadyExists` if another transaction is in progress, or if one
+    /// was interrupted and [`recover_transaction`](#method.recover_transaction) must be called.
+    pub fn transaction(&self) -> Result<Transaction> {
+        let journal_dir = PathArc::new(self.join(JOURNAL_DIR));
+        fs::create_dir(&journal_dir)
+            .map_err(|err| Error::new(err, "creating", journal_dir.clone()))?;
+        let journal_path = journal_dir.join(JOURNAL);
+        let journal = match fs::OpenOptions::new()
+            .append(true)
+            .create_new(true)
+            .open(&journal_path)
+        {
+            Ok(f) => f,
+            Err(err) => {
+                // Don't leave a journal dir behind which blocks the next transaction.
+                let _ = fs::remove_dir_all(&journal_dir);
+                return Err(Error::new(err, "creating", PathArc::new(&journal_path)));
+            }
+        };
+        Ok(Transaction {
+            dir: self.clone(),
+            journal_dir,
+            journal,
+            ops: Vec::new(),
+            done: false,
+        })
+    }
+
+    /// Recover from a [`Transaction`](struct.Transaction.html) which was interrupted by a crash.
+    ///
+    /// If every change of the transaction was applied the journal is simply removed, otherwise
+    /// the changes which were applied are undone. Returns whether there was a transaction to
+    /// recover.
+    pub fn recover_transaction(&self) -> Result<bool> {
+        let journal_dir = PathArc::new(self.join(JOURNAL_DIR));
+        if !journal_dir.exists() {
+            return Ok(false);
+        }
+        rollback(&journal_dir).map_err(|err| err.context("recovering transaction in", self))?;
+        Ok(true)
+    }
+}
+
+impl Transaction {
+    /// Stage writing `contents` to the file at `path`, replacing it if it exists. A relative
+    /// `path` is relative to the directory of the transaction.
+    ///
+    /// The contents are written to the journal directory immediately.
+    pub fn write<P: AsRef<Path>>(&mut self, path: P, contents: &[u8]) -> Result<()> {
+        let staged = self.journal_dir.join(format!("new.{}", self.ops.len()));
+        let write = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&staged)
+            .and_then(|mut f| {
+                f.write_all(contents)?;
+                f.sync_all()
+            });
+        write.map_err(|err| Error::new(err, "writing", PathArc::new(&staged)))?;
+        self.ops.push(Op::Write {
+            path: self.dir.join(path),
+            staged,
+        });
+        Ok(())
+    }
+
+    /// Stage removing the file at `path`. A relative `path` is relative to the directory of the
+    /// transaction.
+    ///
+    /// The commit fails if the file does not exist by then.
+    pub fn remove<P: AsRef<Path>>(&mut self, path: P) {
+        self.ops.push(Op::Remove {
+            path: self.dir.join(path),
+        });
+    }
+
+    /// Stage renaming the file at `from` to `to`, replacing `to` if it exists. Relative paths
+    /// are relative to the directory of the transaction.
+    pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) {
+        self.ops.push(Op::Rename {
+            from: self.dir.join(from),
+            to: self.dir.join(to),
+        });
+    }
+
+    /// Apply all of the staged changes, in the order they were added.
+    ///
+    /// If any change fails, the ones already applied are undone before returning the error.
+    ///
+    /// Once this returns `Ok` the changes are durable. Removing the journal afterwards is best
+    /// effort: if it fails, the next [`transaction`](struct.PathDir.html#method.transaction)
+    /// returns an error until `recover_transaction` is called, which only removes it.
+    pub fn commit(mut self) -> Result<()> {
+        self.done = true;
+        if let Err(err) = self.apply() {
+            // If the rollback fails too the journal is left for `recover_transaction`.
+            let _ = rollback(&self.journal_dir);
+            return Err(err.context("committing transaction in", &self.dir));
+        }
+        let _ = remove_journal(&self.journal_dir);
+        Ok(())
+    }
+
+    fn apply(&mut self) -> Result<()> {
+        // The journal dir itself must be on disk before any rename is, otherwise a crash could
+        // keep the renames but lose the journal, leaving `recover_transaction` nothing to undo.
+        sync_dir(&self.dir)?;
+        sync_dir(&self.journal_dir)?;
+        for i in 0..self.ops.len() {
+            self.apply_op(i)?;
+        }
+        // The renames must be on disk before the commit record is, otherwise a crash could
+        // leave a committed journal whose renames were lost, and recovering would then delete
+        // the staged files and backups.
+        for dir in self.affected_dirs() {
+            sync_dir(&dir)?;
+        }
+        self.record(&[RECORD_COMMIT])
+    }
+
+    /// The directories whose entries are changed by the renames, including the journal dir.
+    fn affected_dirs(&self) -> BTreeSet<PathBuf> {
+        let mut dirs = BTreeSet::new();
+        dirs.insert(self.journal_dir.to_path_buf());
+        for op in &self.ops {
+            let (path, other) = match *op {
+                Op::Write { ref path, .. } | Op::Remove { ref path } => (path, None),
+                Op::Rename { ref from, ref to } => (from, Some(to)),
+            };
+            dirs.extend(
+                Some(path)
+                    .into_iter()
+                    .chain(other)
+                    .filter_map(|p| p.parent())
+                    .map(Path::to_path_buf),
+            );
+        }
+        dirs
+    }
+
+    fn apply_op(&mut self, i: usize) -> Result<()> {
+        let backup = self.journal_dir.join(format!("old.{}", i));
+        let (from, to) = match self.ops[i] {
+            Op::Write {
+                ref path,
+                ref staged,
+            } => (staged.clone(), path.clone()),
+            Op::Remove { ref path } => return self.move_file(path.clone(), backup),
+            Op::Rename { ref from, ref to } => (from.clone(), to.clone()),
+        };
+        if fs::symlink_metadata(&to).is_ok() {
+            self.move_file(to.clone(), backup)?;
+        }
+        self.move_file(from, to)
+    }
+
+    /// Record the rename in the journal, then perform it.
+    fn move_file(&mut self, from: PathArc, to: PathArc) -> Result<()> {
+        let mut record = vec![RECORD_RENAME];
+        encode_path(&mut record, &from);
+        encode_path(&mut record, &to);
+        self.record(&record)?;
+        fs::rename(&from, &to).map_err(|err| Error::with_dest(err, "renaming", from, to))
+    }
+
+    fn record(&mut self, record: &[u8]) -> Result<()> {
+        let journal = &mut self.journal;
+        journal
+            .write_all(record)
+            .and_then(|_| journal.sync_data())
+            .map_err(|err| {
+                Error::new(
+                    err,
+                    "writing to",
+                    PathArc::new(self.journal_dir.join(JOURNAL)),
+                )
+            })
+    }
+}
+
+impl fmt::Debug for Transaction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Transaction(")?;
+        self.dir.fmt(f)?;
+        write!(f, ")")
+    }
+}
+
+impl Drop for Transaction {
+    /// Discard the staged changes if the transaction was not committed.
+    fn drop(&mut self) {
+        if !self.done {
+            let _ = rollback(&self.journal_dir);
+        }
+    }
+}
+
+/// Undo the renames recorded in the journal, unless it was committed, and remove it.
+fn rollback(journal_dir: &PathArc) -> Result<()> {
+    let journal_path = PathArc::new(journal_dir.join(JOURNAL));
+    let (renames, committed) = match fs::read(&journal_path) {
+        Ok(bytes) => parse_journal(&bytes),
+        // Crashed before the journal was created.
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => (Vec::new(), false),
+        Err(err) => return Err(Error::new(err, "reading", journal_path)),
+    };
+    if !committed {
+        let mut dirs = BTreeSet::new();
+        for (from, to) in renames.into_iter().rev() {
+            // Only undo renames which were performed (and not already undone).
+            let performed =
+                fs::symlink_metadata(&to).is_ok() && fs::symlink_metadata(&from).is_err();
+            if performed {
+                fs::rename(&to, &from).map_err(|err| {
+                    Error::with_dest(err, "renaming", PathArc::new(&to), PathArc::new(&from))
+                })?;
+                dirs.extend(from.parent().map(Path::to_path_buf));
+                dirs.extend(to.parent().map(Path::to_path_buf));
+            }
+        }
+        // The undone renames must be on disk before the journal describing them is removed.
+        for dir in dirs {
+            sync_dir(&dir)?;
+        }
+    }
+    remove_journal(journal_dir)
+}
+
+fn remove_journal(journal_dir: &PathArc) -> Result<()> {
+    fs::remove_dir_all(journal_dir).map_err(|err| Error::new(err, "removing", journal_dir.clone()))
+}
+
+/// Parse the renames of the journal and whether it was committed. A truncated record (from a
+/// crash while writing it) is ignored, the rename it describes was never performed.
+fn parse_journal(mut bytes: &[u8]) -> (Vec<(PathBuf, PathBuf)>, bool) {
+    let mut renames = Vec::new();
+    while let Some((&tag, rest)) = bytes.split_first() {
+        bytes = rest;
+        match tag {
+            RECORD_COMMIT => return (renames, true),
+            RECORD_RENAME => {
+                let from = decode_path(&mut bytes);
+                let to = decode_path(&mut bytes);
+                match (from, to) {
+                    (Some(from), Some(to)) => renames.push((from, to)),
+                    _ => break,
+                }
+            }
+            _ => break,
+        }
+    }
+    (renames, false)
+}
+
+fn encode_path(out: &mut Vec<u8>, path: &Path) {
+    let bytes = os_str_bytes(path.as_os_str());
+    out.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+    out.extend_from_slice(&bytes);
+}
+
+fn decode_path(bytes: &mut &[u8]) -> Option<PathBuf> {
+    if bytes.len() < 8 {
+        return None;
+    }
+    let (len, rest) = bytes.split_at(8);
+    let mut len_bytes = [0; 8];
+    len_bytes.copy_from_slice(len);
+    let len = u64::from_le_bytes(len_bytes) as usize;
+    if rest.len() < len {
+        return None;
+    }
+    let (path, rest) = rest.split_at(len);
+    *bytes = rest;
+    Some(PathBuf::from(os_string_from_bytes(path)))
+}
+
+/// Make sure the entries of the directory (i.e. the staged files) are on disk.
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> Result<()> {
+    fs::File::open(dir)
+        .and_then(|f| f.sync_all())
+        .map_err(|err| Error::new(err, "syncing", PathArc::new(dir)))
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem;
+    use tempdir::TempDir;
+
+    use super::super::{PathDir, PathFile};
+    use super::*;
+
+    fn read(dir: &PathDir, name: &str) -> String {
+        PathFile::new(dir.join(name))
+            .unwrap()
+            .read_string()
+            .unwrap()
+    }
+
+    fn setup(tmp_dir: &TempDir) -> PathDir {
+        let dir = PathDir::new(tmp_dir.path()).unwrap();
+        PathFile::create(dir.join("a.txt"))
+            .unwrap()
+            .write_str("a")
+            .unwrap();
+        PathFile::create(dir.join("b.txt"))
+            .unwrap()
+            .write_str("b")
+            .unwrap();
+        dir
+    }
+
+    #[test]
+    fn transaction_commit() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let dir = setup(&tmp_dir);
+
+        let mut tx = dir.transaction().unwrap();
+        // only one transaction at a time
+        assert!(dir.transaction().is_err());
+        tx.write("a.txt", b"A").unwrap();
+        tx.rename("b.txt", "c.txt");
+        tx.write("b.txt", b"B").unwrap();
+        let dirs: Vec<_> = tx.affected_dirs().into_iter().collect();
+        assert_eq!(vec![dir.to_path_buf(), dir.join(JOURNAL_DIR).to_path_buf()], dirs);
+        tx.commit().unwrap();
+
+        assert_eq!("A", read(&dir, "a.txt"));
+        assert_eq!("B", read(&dir, "b.txt"));
+        assert_eq!("b", read(&dir, "c.txt"));
+        assert!(!dir.join(JOURNAL_DIR).exists());
+    }
+
+    #[test]
+    fn transaction_rollback() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let dir = setup(&tmp_dir);
+
+        // dropped without commit
+        {
+            let mut tx = dir.transaction().unwrap();
+            tx.write("a.txt", b"A").unwrap();
+            tx.remove("b.txt");
+        }
+        assert_eq!("a", read(&dir, "a.txt"));
+        assert_eq!("b", read(&dir, "b.txt"));
+        assert!(!dir.join(JOURNAL_DIR).exists());
+
+        // a failing change undoes the previous ones
+        let mut tx = dir.transaction().unwrap();
+        tx.write("a.txt", b"A").unwrap();
+        tx.write("new.txt", b"new").unwrap();
+        tx.remove("b.txt");
+        tx.remove("missing.txt");
+        let err = tx.commit().unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.io_error().kind());
+        assert_eq!("committing transaction in", err.contexts()[0].action());
+
+        assert_eq!("a", read(&dir, "a.txt"));
+        assert_eq!("b", read(&dir, "b.txt"));
+        assert!(!dir.join("new.txt").exists());
+        assert!(!dir.join(JOURNAL_DIR).exists());
+    }
+
+    #[test]
+    fn transaction_recover() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let dir = setup(&tmp_dir);
+        assert!(!dir.recover_transaction().unwrap());
+
+        // crash after the first change was applied
+        let mut tx = dir.transaction().unwrap();
+        tx.write("a.txt", b"A").unwrap();
+        tx.write("b.txt", b"B").unwrap();
+        tx.apply_op(0).unwrap();
+        mem::forget(tx);
+        assert_eq!("A", read(&dir, "a.txt"));
+
+        assert!(dir.transaction().is_err());
+        assert!(dir.recover_transaction().unwrap());
+        assert_eq!("a", read(&dir, "a.txt"));
+        assert_eq!("b", read(&dir, "b.txt"));
+
+        // crash after the commit was recorded
+        let mut tx = dir.transaction().unwrap();
+        tx.write("a.txt", b"A").unwrap();
+        tx.apply().unwrap();
+        mem::forget(tx);
+
+        assert!(dir.recover_transaction().unwrap());
+        assert_eq!("A", read(&dir, "a.txt"));
+        assert!(!dir.join(JOURNAL_DIR).exists());
+    }
+
+    #[test]
+    fn journal_truncated() {
+        let mut bytes = vec![RECORD_RENAME];
+        encode_path(&mut bytes, Path::new("/a"));
+        encode_path(&mut bytes, Path::new("/b"));
+        let full = bytes.len();
+        encode_path(&mut bytes, Path::new("/c"));
+
+        let (renames, committed) = parse_journal(&bytes[..full]);
+        assert_eq!(vec![(PathBuf::from("/a"), PathBuf::from("/b"))], renames);
+        assert!(!committed);
+
+        let mut partial = bytes[..full].to_vec();
+        partial.push(RECORD_RENAME);
+        partial.extend_from_slice(&bytes[full..full + 3]);
+        assert_eq!(1, parse_journal(&partial).0.len());
+    }
+}
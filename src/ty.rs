@@ -5,16 +5,20 @@
  * http://opensource.org/licenses/MIT>, at your option. This file may not be
  * copied, modified, or distributed except according to those terms.
  */
+use std::fs;
 use std::io;
+use std::convert::TryFrom;
+use std::str::FromStr;
 use std_prelude::*;
 
 use super::{Error, Result};
-use super::{PathAbs, PathArc, PathDir, PathFile};
+use super::{PathAbs, PathArc, PathDir, PathFile, PathOther};
+use other::OtherKind;
 
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "serialize", serde(tag = "type", content = "path", rename_all = "lowercase"))]
 #[derive(Debug, Clone, Eq, Hash, PartialEq, PartialOrd, Ord)]
-/// An an enum containing either a file or a directory.
+/// An an enum containing either a file, a directory or a special file (i.e. a socket).
 ///
 /// This is used primarily for:
 /// - The items returned from `PathDir::list`
@@ -25,13 +29,13 @@ use super::{PathAbs, PathArc, PathDir, PathFile};
 pub enum PathType {
     File(PathFile),
     Dir(PathDir),
+    Other(PathOther),
 }
 
 impl PathType {
     /// Resolves and returns the `PathType` of the given path.
     ///
-    /// > If the path exists but is not a file or a directory (i.e. is a symlink), then
-    /// > `io::ErrorKind::InvalidInput` is returned.
+    /// FIFOs, sockets and devices are returned as `PathType::Other`.
     ///
     /// # Examples
     /// ```rust
@@ -49,10 +53,17 @@ impl PathType {
     /// Consume the `PathAbs` returning the `PathType`.
     pub fn from_abs(abs: PathAbs) -> Result<PathType> {
         let ty = abs.metadata()?.file_type();
+        PathType::from_abs_type(abs, &ty)
+    }
+
+    /// Consume the `PathAbs` returning the `PathType`, given its (already known) file type.
+    pub(crate) fn from_abs_type(abs: PathAbs, ty: &fs::FileType) -> Result<PathType> {
         if ty.is_file() {
             Ok(PathType::File(PathFile(abs)))
         } else if ty.is_dir() {
             Ok(PathType::Dir(PathDir(abs)))
+        } else if let Some(kind) = OtherKind::from_file_type(ty) {
+            Ok(PathType::Other(PathOther::from_abs_unchecked(abs, kind)))
         } else {
             Err(Error::new(
                 io::Error::new(io::ErrorKind::InvalidInput, "path is not a dir or a file"),
@@ -62,8 +73,107 @@ impl PathType {
         }
     }
 
+    /// Get a reference to the `PathFile`, if this is a file.
+    pub fn as_file(&self) -> Option<&PathFile> {
+        match *self {
+            PathType::File(ref f) => Some(f),
+            _ => None,
+        }
+    }
+
+    /// Get a reference to the `PathDir`, if this is a directory.
+    pub fn as_dir(&self) -> Option<&PathDir> {
+        match *self {
+            PathType::Dir(ref d) => Some(d),
+            _ => None,
+        }
+    }
+
+    /// Get a reference to the `PathOther`, if this is a special file.
+    pub fn as_other(&self) -> Option<&PathOther> {
+        match *self {
+            PathType::Other(ref o) => Some(o),
+            _ => None,
+        }
+    }
+
+    /// Convert the `PathType` into a `PathFile`.
+    ///
+    /// > If it is not a file then `io::ErrorKind::InvalidInput` is returned.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// use path_abs::PathType;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let lib = PathType::new("src/lib.rs")?.into_file()?;
+    ///
+    /// let err = PathType::new("src")?.into_file().unwrap_err();
+    /// assert!(err.to_string().starts_with("expected a file, found a directory"));
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn into_file(self) -> Result<PathFile> {
+        match self {
+            PathType::File(f) => Ok(f),
+            ty => Err(ty.unexpected("a file")),
+        }
+    }
+
+    /// Convert the `PathType` into a `PathDir`.
+    ///
+    /// > If it is not a directory then `io::ErrorKind::InvalidInput` is returned.
+    pub fn into_dir(self) -> Result<PathDir> {
+        match self {
+            PathType::Dir(d) => Ok(d),
+            ty => Err(ty.unexpected("a directory")),
+        }
+    }
+
+    /// The error for converting to the `expected` type.
+    fn unexpected(self, expected: &str) -> Error {
+        let found = match self {
+            PathType::File(_) => "a file".to_string(),
+            PathType::Dir(_) => "a directory".to_string(),
+            PathType::Other(ref o) => format!("a {}", o.kind()),
+        };
+        Error::new(
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("expected {}, found {}", expected, found),
+            ),
+            "converting",
+            self.into(),
+        )
+    }
+
+    /// Call `f` with the `PathFile` if this is a file, returning its result.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// use path_abs::PathType;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let len = PathType::new("src/lib.rs")?.map_file(|f| f.metadata().map(|m| m.len()));
+    /// assert!(len.is_some());
+    /// assert!(PathType::new("src")?.map_file(|f| f.metadata()).is_none());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn map_file<T, F: FnOnce(&PathFile) -> T>(&self, f: F) -> Option<T> {
+        self.as_file().map(f)
+    }
+
+    /// Call `f` with the `PathDir` if this is a directory, returning its result.
+    pub fn map_dir<T, F: FnOnce(&PathDir) -> T>(&self, f: F) -> Option<T> {
+        self.as_dir().map(f)
+    }
+
     /// Unwrap the `PathType` as a `PathFile`.
     ///
+    /// > This panics if the path is a directory, use [`into_file`](#method.into_file) to get an
+    /// > error instead.
+    ///
     /// # Examples
     /// ```rust
     /// # extern crate path_abs;
@@ -75,14 +185,15 @@ impl PathType {
     pub fn unwrap_file(self) -> PathFile {
         match self {
             PathType::File(f) => f,
-            PathType::Dir(d) => {
-                panic!("unwrap_file called on {}, which is not a file", d.display())
-            }
+            ty => panic!("unwrap_file called on {}, which is not a file", ty.display()),
         }
     }
 
     /// Unwrap the `PathType` as a `PathDir`.
     ///
+    /// > This panics if the path is a file, use [`into_dir`](#method.into_dir) to get an error
+    /// > instead.
+    ///
     /// # Examples
     /// ```rust
     /// # extern crate path_abs;
@@ -94,29 +205,26 @@ impl PathType {
     pub fn unwrap_dir(self) -> PathDir {
         match self {
             PathType::Dir(d) => d,
-            PathType::File(f) => panic!(
+            ty => panic!(
                 "unwrap_dir called on {}, which is not a directory",
-                f.display()
+                ty.display()
             ),
         }
     }
 
     /// Return whether this variant is `PathType::Dir`.
     pub fn is_dir(&self) -> bool {
-        if let PathType::Dir(_) = *self {
-            true
-        } else {
-            false
-        }
+        matches!(*self, PathType::Dir(_))
     }
 
     /// Return whether this variant is `PathType::File`.
     pub fn is_file(&self) -> bool {
-        if let PathType::File(_) = *self {
-            true
-        } else {
-            false
-        }
+        matches!(*self, PathType::File(_))
+    }
+
+    /// Return whether this variant is `PathType::Other`.
+    pub fn is_other(&self) -> bool {
+        matches!(*self, PathType::Other(_))
     }
 
     /// Create a mock file type. *For use in tests only*.
@@ -139,6 +247,7 @@ impl AsRef<PathAbs> for PathType {
         match *self {
             PathType::File(ref file) => file.as_ref(),
             PathType::Dir(ref dir) => dir.as_ref(),
+            PathType::Other(ref other) => other.as_ref(),
         }
     }
 }
@@ -197,31 +306,31 @@ impl Borrow<PathBuf> for PathType {
     }
 }
 
-impl<'a> Borrow<PathAbs> for &'a PathType {
+impl Borrow<PathAbs> for &PathType {
     fn borrow(&self) -> &PathAbs {
         self.as_ref()
     }
 }
 
-impl<'a> Borrow<PathArc> for &'a PathType {
+impl Borrow<PathArc> for &PathType {
     fn borrow(&self) -> &PathArc {
         self.as_ref()
     }
 }
 
-impl<'a> Borrow<Path> for &'a PathType {
+impl Borrow<Path> for &PathType {
     fn borrow(&self) -> &Path {
         self.as_ref()
     }
 }
 
-impl<'a> Borrow<PathBuf> for &'a PathType {
+impl Borrow<PathBuf> for &PathType {
     fn borrow(&self) -> &PathBuf {
         self.as_ref()
     }
 }
 
-impl Into<PathAbs> for PathType {
+impl From<PathType> for PathAbs {
     /// Downgrades the `PathType` into a `PathAbs`
     ///
     /// # Examples
@@ -235,23 +344,24 @@ impl Into<PathAbs> for PathType {
     /// let abs: PathAbs = ty.into();
     /// # Ok(()) } fn main() { try_main().unwrap() }
     /// ```
-    fn into(self) -> PathAbs {
-        match self {
+    fn from(ty: PathType) -> PathAbs {
+        match ty {
             PathType::File(p) => p.into(),
             PathType::Dir(p) => p.into(),
+            PathType::Other(p) => p.into(),
         }
     }
 }
 
-impl Into<PathArc> for PathType {
+impl From<PathType> for PathArc {
     /// Downgrades the `PathType` into a `PathArc`
-    fn into(self) -> PathArc {
-        let abs: PathAbs = self.into();
+    fn from(ty: PathType) -> PathArc {
+        let abs: PathAbs = ty.into();
         abs.into()
     }
 }
 
-impl Into<PathBuf> for PathType {
+impl From<PathType> for PathBuf {
     /// Downgrades the `PathType` into a `PathBuf`. Avoids a clone if this is the only reference.
     ///
     /// # Examples
@@ -265,8 +375,41 @@ impl Into<PathBuf> for PathType {
     /// let buf: PathBuf = ty.into();
     /// # Ok(()) } fn main() { try_main().unwrap() }
     /// ```
-    fn into(self) -> PathBuf {
-        let arc: PathArc = self.into();
+    fn from(ty: PathType) -> PathBuf {
+        let arc: PathArc = ty.into();
         arc.into()
     }
 }
+
+impl FromStr for PathType {
+    type Err = Error;
+
+    /// Equivalent to [`PathType::new`](#method.new).
+    fn from_str(s: &str) -> Result<PathType> {
+        PathType::new(s)
+    }
+}
+
+impl<'a> TryFrom<&'a Path> for PathType {
+    type Error = Error;
+
+    fn try_from(path: &'a Path) -> Result<PathType> {
+        PathType::new(path)
+    }
+}
+
+impl TryFrom<PathBuf> for PathType {
+    type Error = Error;
+
+    fn try_from(path: PathBuf) -> Result<PathType> {
+        PathType::new(path)
+    }
+}
+
+impl TryFrom<PathArc> for PathType {
+    type Error = Error;
+
+    fn try_from(path: PathArc) -> Result<PathType> {
+        PathType::new(path)
+    }
+}
@@ -0,0 +1,313 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Computing the disk usage of directory trees.
+
+use std::collections::HashSet;
+use std::fs;
+use std_prelude::*;
+
+use super::{Error, Result};
+use super::{PathArc, PathDir};
+use utils;
+
+/// Options which can be used to configure how
+/// [`PathDir::disk_usage_with`](struct.PathDir.html#method.disk_usage_with) computes the disk
+/// usage.
+#[derive(Debug, Clone, Default)]
+pub struct DiskUsageOptions {
+    one_file_system: bool,
+}
+
+impl DiskUsageOptions {
+    /// Create a blank set of options.
+    pub fn new() -> DiskUsageOptions {
+        DiskUsageOptions::default()
+    }
+
+    /// Set whether to skip directories which are on a different filesystem than the root
+    /// directory (i.e. mount points). This is like `du -x`.
+    ///
+    /// > This has no effect on windows.
+    pub fn one_file_system(&mut self, one_file_system: bool) -> &mut DiskUsageOptions {
+        self.one_file_system = one_file_system;
+        self
+    }
+}
+
+/// The disk usage of a directory tree, returned by
+/// [`PathDir::disk_usage`](struct.PathDir.html#method.disk_usage).
+#[derive(Debug)]
+pub struct DiskUsage {
+    total: Usage,
+    children: Vec<(OsString, Usage)>,
+    errors: Vec<Error>,
+}
+
+impl DiskUsage {
+    /// The usage of the whole tree, including the directory itself.
+    pub fn total(&self) -> Usage {
+        self.total
+    }
+
+    /// The usage of each direct child of the directory, sorted by name.
+    ///
+    /// A file with multiple hard links is only counted for the first child (in this order)
+    /// which contains it.
+    pub fn children(&self) -> &[(OsString, Usage)] {
+        &self.children
+    }
+
+    /// The errors which occurred while computing the usage, for instance because a
+    /// subdirectory could not be read. The entries that caused them are not counted.
+    pub fn errors(&self) -> &[Error] {
+        &self.errors
+    }
+}
+
+/// The amount of space used by files.
+#[derive(Debug, Clone, Copy, Default, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub struct Usage {
+    /// The sum of the lengths of the files, in bytes.
+    pub apparent: u64,
+    /// The amount of space actually allocated on disk, in bytes.
+    ///
+    /// This can be smaller than `apparent` for sparse or compressed files and is usually larger
+    /// for small files. On windows this is the same as `apparent`.
+    pub allocated: u64,
+}
+
+impl Usage {
+    fn add(&mut self, other: Usage) {
+        self.apparent += other.apparent;
+        self.allocated += other.allocated;
+    }
+}
+
+impl PathDir {
+    /// Compute the disk usage of the directory tree, like `du`.
+    ///
+    /// Symlinks are never followed and files with multiple hard links are only counted once.
+    ///
+    /// Errors from reading the contents of the tree are collected in [`DiskUsage::errors`]
+    /// instead of aborting the computation. Only failing to read `self` returns an error.
+    ///
+    /// [`DiskUsage::errors`]: struct.DiskUsage.html#method.errors
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let tmp = PathDir::new(tmp.path())?;
+    /// let dir = PathDir::create(tmp.join("dir"))?;
+    /// PathFile::create(dir.join("foo.txt"))?.write_str("foo")?;
+    /// let sub = PathDir::create(dir.join("sub"))?;
+    /// PathFile::create(sub.join("bar.txt"))?.write_str("bar bar")?;
+    ///
+    /// let usage = dir.disk_usage()?;
+    /// assert!(usage.total().apparent >= 3 + 7);
+    /// assert_eq!(usage.children()[0].0, "foo.txt");
+    /// assert_eq!(usage.children()[0].1.apparent, 3);
+    /// assert!(usage.errors().is_empty());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn disk_usage(&self) -> Result<DiskUsage> {
+        self.disk_usage_with(&DiskUsageOptions::new())
+    }
+
+    /// Compute the disk usage of the directory tree with the given options.
+    ///
+    /// See [`disk_usage`](#method.disk_usage).
+    pub fn disk_usage_with(&self, options: &DiskUsageOptions) -> Result<DiskUsage> {
+        let meta = self.symlink_metadata()?;
+        let mut walk = Walk {
+            one_file_system: options.one_file_system,
+            root_dev: dev(&meta),
+            seen: HashSet::new(),
+            errors: Vec::new(),
+        };
+
+        let mut total = walk.usage_of(&meta);
+        let mut entries = read_dir(self)?;
+        entries.sort();
+
+        let mut children = Vec::with_capacity(entries.len());
+        for (name, path) in entries {
+            let usage = walk.walk(path);
+            total.add(usage);
+            children.push((name, usage));
+        }
+
+        Ok(DiskUsage {
+            total,
+            children,
+            errors: walk
+                .errors
+                .into_iter()
+                .map(|err| err.context("computing disk usage of", self))
+                .collect(),
+        })
+    }
+}
+
+struct Walk {
+    one_file_system: bool,
+    root_dev: u64,
+    seen: HashSet<(u64, u64)>,
+    errors: Vec<Error>,
+}
+
+impl Walk {
+    /// Compute the usage of `path`, recursively if it is a directory.
+    fn walk(&mut self, path: PathArc) -> Usage {
+        let meta = match path.symlink_metadata() {
+            Ok(m) => m,
+            Err(err) => {
+                self.errors.push(err);
+                return Usage::default();
+            }
+        };
+        if !meta.is_dir() {
+            return self.usage_of(&meta);
+        }
+        if self.one_file_system && dev(&meta) != self.root_dev {
+            return Usage::default();
+        }
+
+        let mut usage = self.usage_of(&meta);
+        match read_dir(&path) {
+            Ok(entries) => {
+                for (_, child) in entries {
+                    let child_usage = self.walk(child);
+                    usage.add(child_usage);
+                }
+            }
+            Err(err) => self.errors.push(err),
+        }
+        usage
+    }
+
+    /// The usage of a single entry, or nothing if it was already counted through a hard link.
+    fn usage_of(&mut self, meta: &fs::Metadata) -> Usage {
+        if let Some(id) = hard_link_id(meta) {
+            if !self.seen.insert(id) {
+                return Usage::default();
+            }
+        }
+        Usage {
+            apparent: meta.len(),
+            allocated: allocated(meta),
+        }
+    }
+}
+
+fn read_dir(dir: &PathArc) -> Result<Vec<(OsString, PathArc)>> {
+    let mut out = Vec::new();
+    for entry in utils::read_dir(dir)? {
+        let entry = entry?;
+        out.push((entry.file_name(), PathArc::from(entry.path())));
+    }
+    Ok(out)
+}
+
+#[cfg(unix)]
+fn dev(meta: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    meta.dev()
+}
+
+#[cfg(windows)]
+fn dev(_meta: &fs::Metadata) -> u64 {
+    0
+}
+
+#[cfg(unix)]
+fn hard_link_id(meta: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    if meta.nlink() > 1 && !meta.is_dir() {
+        Some((meta.dev(), meta.ino()))
+    } else {
+        None
+    }
+}
+
+#[cfg(windows)]
+fn hard_link_id(_meta: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+#[cfg(unix)]
+fn allocated(meta: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    // `st_blocks` is always in units of 512 bytes, regardless of the block size.
+    meta.blocks() * 512
+}
+
+#[cfg(windows)]
+fn allocated(meta: &fs::Metadata) -> u64 {
+    meta.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::super::{PathDir, PathFile};
+
+    #[cfg(unix)]
+    #[test]
+    fn disk_usage_hard_links() {
+        use std::fs;
+
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let dir = PathDir::create(tmp_abs.join("dir")).unwrap();
+        let a = PathDir::create(dir.join("a")).unwrap();
+        let b = PathDir::create(dir.join("b")).unwrap();
+
+        let foo = PathFile::create(a.join("foo.txt")).unwrap();
+        foo.write_str(&"x".repeat(10_000)).unwrap();
+        fs::hard_link(&foo, b.join("foo.txt")).unwrap();
+
+        let usage = dir.disk_usage().unwrap();
+        let children = usage.children();
+        assert_eq!(2, children.len());
+        assert_eq!(10_000, children[0].1.apparent - a.metadata().unwrap().len());
+        assert_eq!(0, children[1].1.apparent - b.metadata().unwrap().len());
+        assert!(usage.total().allocated >= 10_000);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn disk_usage_collects_errors() {
+        use std::fs;
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let locked = PathDir::create(tmp_abs.join("locked")).unwrap();
+        PathFile::create(locked.join("foo.txt")).unwrap();
+        PathFile::create(tmp_abs.join("bar.txt")).unwrap().write_str("bar").unwrap();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+
+        let usage = tmp_abs.disk_usage().unwrap();
+        // root can read the directory anyway
+        let readable = fs::read_dir(&locked).is_ok();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert_eq!(3, usage.children()[0].1.apparent);
+        if !readable {
+            assert_eq!(1, usage.errors().len());
+            assert_eq!(locked.as_path(), usage.errors()[0].path());
+            assert_eq!(tmp_abs.as_path(), usage.errors()[0].contexts()[0].path());
+        }
+    }
+}
@@ -0,0 +1,121 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Helpers shared by the modules of the crate.
+
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std_prelude::*;
+
+use super::{Error, PathArc, Result};
+
+/// Size of the buffers used when reading files in chunks.
+pub(crate) const BUF_SIZE: usize = 64 * 1024;
+
+/// Counter making the names returned by `temp_path` unique within the process.
+static TEMP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// A hidden path next to `path`, for writing a file which is then renamed to `path`.
+///
+/// The name contains the pid and a counter so it is unique among the threads and processes
+/// writing to the same directory. A stale file left there by a previous process with the same
+/// pid is removed.
+pub(crate) fn temp_path(path: &Path) -> Result<PathArc> {
+    let count = TEMP_COUNT.fetch_add(1, Ordering::Relaxed);
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(".{}.{}.tmp", process::id(), count));
+    let tmp = PathArc::new(path.with_file_name(name));
+    match fs::remove_file(&tmp) {
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(tmp),
+        Err(err) => Err(Error::new(err, "removing", tmp)),
+        Ok(()) => Ok(tmp),
+    }
+}
+
+/// Open the directory `dir` for iterating over its entries. Errors, including the ones returned
+/// while iterating, are annotated with the path of `dir`.
+pub(crate) fn read_dir(dir: &PathArc) -> Result<ReadDir> {
+    let read = fs::read_dir(dir).map_err(|err| Error::new(err, "reading dir", dir.clone()))?;
+    Ok(ReadDir {
+        dir: dir.clone(),
+        read,
+    })
+}
+
+/// An iterator over the entries of a directory, returned by `read_dir`.
+pub(crate) struct ReadDir {
+    dir: PathArc,
+    read: fs::ReadDir,
+}
+
+impl Iterator for ReadDir {
+    type Item = Result<fs::DirEntry>;
+
+    fn next(&mut self) -> Option<Result<fs::DirEntry>> {
+        let dir = &self.dir;
+        self.read
+            .next()
+            .map(|entry| entry.map_err(|err| Error::new(err, "iterating over", dir.clone())))
+    }
+}
+
+/// The raw bytes of `s`. On windows these are its UTF-16 code units in little endian.
+#[cfg(unix)]
+pub(crate) fn os_str_bytes(s: &OsStr) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    s.as_bytes().to_vec()
+}
+
+/// The raw bytes of `s`. On windows these are its UTF-16 code units in little endian.
+#[cfg(windows)]
+pub(crate) fn os_str_bytes(s: &OsStr) -> Vec<u8> {
+    use std::os::windows::ffi::OsStrExt;
+    s.encode_wide().flat_map(|c| c.to_le_bytes()).collect()
+}
+
+/// The inverse of `os_str_bytes`.
+#[cfg(unix)]
+pub(crate) fn os_string_from_bytes(bytes: &[u8]) -> OsString {
+    use std::os::unix::ffi::OsStrExt;
+    OsStr::from_bytes(bytes).to_os_string()
+}
+
+/// The inverse of `os_str_bytes`.
+#[cfg(windows)]
+pub(crate) fn os_string_from_bytes(bytes: &[u8]) -> OsString {
+    use std::os::windows::ffi::OsStringExt;
+    let wide: Vec<u16> = bytes
+        .chunks(2)
+        .map(|c| u16::from_le_bytes([c[0], c.get(1).cloned().unwrap_or(0)]))
+        .collect();
+    OsString::from_wide(&wide)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::super::PathDir;
+    use super::*;
+
+    #[test]
+    fn sanity_temp_path() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let foo = tmp_abs.join("foo.txt");
+
+        let first = temp_path(&foo).unwrap();
+        let second = temp_path(&foo).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(tmp_abs.as_path(), first.parent().unwrap());
+        assert!(first.file_name().unwrap().to_string_lossy().starts_with(".foo.txt."));
+    }
+}
@@ -0,0 +1,361 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Walking directory trees in parallel.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std_prelude::*;
+
+use super::Result;
+use super::{PathDir, PathType};
+use list::entry_type;
+use utils::{read_dir, ReadDir};
+
+/// What a [`PathDir::walk_parallel`](struct.PathDir.html#method.walk_parallel) visitor wants
+/// to happen next.
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub enum WalkState {
+    /// Keep walking, descending into the directory which was just visited.
+    Continue,
+    /// Keep walking, but don't descend into the directory which was just visited. This is the
+    /// same as `Continue` for anything else.
+    Skip,
+    /// Stop walking as soon as possible.
+    Quit,
+}
+
+/// Options which can be used to configure how
+/// [`PathDir::walk_parallel_with`](struct.PathDir.html#method.walk_parallel_with) walks a
+/// directory tree.
+#[derive(Debug, Clone, Default)]
+pub struct WalkOptions {
+    threads: usize,
+}
+
+impl WalkOptions {
+    /// Create a blank set of options.
+    pub fn new() -> WalkOptions {
+        WalkOptions::default()
+    }
+
+    /// Set the number of threads to use. `0` (the default) uses the available parallelism of
+    /// the machine.
+    pub fn threads(&mut self, threads: usize) -> &mut WalkOptions {
+        self.threads = threads;
+        self
+    }
+}
+
+impl PathDir {
+    /// Walk the directory tree using a pool of threads, calling `visit` with every entry below
+    /// the directory (but not the directory itself).
+    ///
+    /// Entries are resolved like [`list`](#method.list) does, and any error below the directory
+    /// is passed to `visit` instead of stopping the walk. Entries are visited in no particular
+    /// order. Symlinks to directories are visited but not descended into.
+    ///
+    /// If `visit` panics the walk stops and the panic is propagated once every thread is done.
+    ///
+    /// The [`WalkState`](enum.WalkState.html) returned by `visit` can skip descending into a
+    /// directory or stop the walk. After a `Quit`, entries which other threads are already
+    /// visiting are still finished.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::sync::Mutex;
+    /// use path_abs::{PathDir, PathFile, PathType, WalkState};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let tmp = PathDir::new(tmp.path())?;
+    /// PathFile::create(PathDir::create_all(tmp.join("src/bin"))?.join("main.rs"))?;
+    /// PathFile::create(PathDir::create_all(tmp.join("target/debug"))?.join("main"))?;
+    ///
+    /// let files = Mutex::new(Vec::new());
+    /// tmp.walk_parallel(|entry| {
+    ///     match entry {
+    ///         Ok(PathType::Dir(ref d)) if d.ends_with("target") => return WalkState::Skip,
+    ///         Ok(PathType::File(f)) => files.lock().unwrap().push(f),
+    ///         _ => {}
+    ///     }
+    ///     WalkState::Continue
+    /// })?;
+    ///
+    /// let files = files.into_inner().unwrap();
+    /// assert_eq!(vec![PathFile::new(tmp.join("src/bin/main.rs"))?], files);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn walk_parallel<F>(&self, visit: F) -> Result<()>
+    where
+        F: Fn(Result<PathType>) -> WalkState + Sync,
+    {
+        self.walk_parallel_with(&WalkOptions::default(), visit)
+    }
+
+    /// Walk the directory tree like [`walk_parallel`](#method.walk_parallel) with the given
+    /// options.
+    ///
+    /// Only errors reading the directory itself are returned.
+    pub fn walk_parallel_with<F>(&self, options: &WalkOptions, visit: F) -> Result<()>
+    where
+        F: Fn(Result<PathType>) -> WalkState + Sync,
+    {
+        let read = read_dir(self).map_err(|err| err.context("walking", self))?;
+        let threads = match options.threads {
+            0 => thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            n => n,
+        };
+
+        let walk = Walk {
+            root: self,
+            visit,
+            queue: Mutex::new(Queue {
+                jobs: vec![Job::Opened(self.clone(), read)].into(),
+                active: 0,
+            }),
+            ready: Condvar::new(),
+            quit: AtomicBool::new(false),
+        };
+        thread::scope(|s| {
+            for _ in 0..threads {
+                s.spawn(|| walk.run());
+            }
+        });
+        Ok(())
+    }
+}
+
+struct Walk<'a, F> {
+    root: &'a PathDir,
+    visit: F,
+    queue: Mutex<Queue>,
+    ready: Condvar,
+    quit: AtomicBool,
+}
+
+struct Queue {
+    jobs: VecDeque<Job>,
+    /// The number of threads currently reading a directory, which may push more jobs.
+    active: usize,
+}
+
+enum Job {
+    Opened(PathDir, ReadDir),
+    Dir(PathDir),
+}
+
+impl<'a, F> Walk<'a, F>
+where
+    F: Fn(Result<PathType>) -> WalkState + Sync,
+{
+    fn run(&self) {
+        while let Some(job) = self.next_job() {
+            let _active = Active {
+                queue: &self.queue,
+                ready: &self.ready,
+                quit: &self.quit,
+            };
+            self.read(job);
+        }
+    }
+
+    /// Wait for the next job, returning `None` when the walk is done.
+    fn next_job(&self) -> Option<Job> {
+        let mut queue = self.queue.lock().expect("poisoned");
+        loop {
+            if self.quit.load(Ordering::SeqCst) {
+                return None;
+            }
+            if let Some(job) = queue.jobs.pop_front() {
+                queue.active += 1;
+                return Some(job);
+            }
+            if queue.active == 0 {
+                return None;
+            }
+            queue = self.ready.wait(queue).expect("poisoned");
+        }
+    }
+
+    fn read(&self, job: Job) {
+        let (dir, read) = match job {
+            Job::Opened(dir, read) => (dir, read),
+            Job::Dir(dir) => match read_dir(&dir) {
+                Ok(read) => (dir, read),
+                Err(err) => {
+                    self.visit(Err(err), false);
+                    return;
+                }
+            },
+        };
+        for entry in read {
+            if self.quit.load(Ordering::SeqCst) {
+                return;
+            }
+            let entry = match entry {
+                Ok(e) => e,
+                Err(err) => {
+                    self.visit(Err(err), false);
+                    continue;
+                }
+            };
+            // Don't descend into symlinks, they could create cycles.
+            let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+            let ty = entry_type(&dir, &entry, true);
+            let subdir = match ty {
+                Ok(PathType::Dir(ref d)) if is_dir => Some(d.clone()),
+                _ => None,
+            };
+            if self.visit(ty, subdir.is_some()) {
+                let mut queue = self.queue.lock().expect("poisoned");
+                queue.jobs.extend(subdir.map(Job::Dir));
+                self.ready.notify_one();
+            }
+        }
+    }
+
+    /// Visit the entry, returning whether to descend into it.
+    fn visit(&self, entry: Result<PathType>, descend: bool) -> bool {
+        let entry = entry.map_err(|err| err.context("walking", self.root));
+        match (self.visit)(entry) {
+            WalkState::Continue => descend,
+            WalkState::Skip => false,
+            WalkState::Quit => {
+                self.quit.store(true, Ordering::SeqCst);
+                // Wake up the idle threads so they can exit.
+                let _queue = self.queue.lock().expect("poisoned");
+                self.ready.notify_all();
+                false
+            }
+        }
+    }
+}
+
+/// Marks a job as done when dropped, even if the visitor panicked. Otherwise the other threads
+/// would wait for it forever.
+struct Active<'a> {
+    queue: &'a Mutex<Queue>,
+    ready: &'a Condvar,
+    quit: &'a AtomicBool,
+}
+
+impl<'a> Drop for Active<'a> {
+    fn drop(&mut self) {
+        let mut queue = self.queue.lock().unwrap_or_else(|err| err.into_inner());
+        queue.active -= 1;
+        if thread::panicking() {
+            self.quit.store(true, Ordering::SeqCst);
+            self.ready.notify_all();
+        } else if queue.active == 0 && queue.jobs.is_empty() {
+            self.ready.notify_all();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use tempdir::TempDir;
+
+    use super::super::PathFile;
+    use super::*;
+
+    fn setup(tmp_dir: &TempDir) -> PathDir {
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        for a in 0..4 {
+            for b in 0..4 {
+                let dir = PathDir::create_all(tmp_abs.join(format!("{}/{}", a, b))).unwrap();
+                PathFile::create(dir.join("file.txt")).unwrap();
+            }
+        }
+        tmp_abs
+    }
+
+    #[test]
+    fn walk_parallel() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = setup(&tmp_dir);
+
+        for &threads in &[1, 4] {
+            let seen = Mutex::new(Vec::new());
+            tmp_abs
+                .walk_parallel_with(WalkOptions::new().threads(threads), |entry| {
+                    let entry = entry.unwrap();
+                    let rel = entry.strip_prefix(&tmp_abs).unwrap().to_path_buf();
+                    seen.lock().unwrap().push(rel);
+                    if entry.ends_with("0/0") {
+                        WalkState::Skip
+                    } else {
+                        WalkState::Continue
+                    }
+                })
+                .unwrap();
+
+            let mut seen = seen.into_inner().unwrap();
+            seen.sort();
+            // 4 top level dirs, 16 sub dirs and 15 files (one was skipped)
+            assert_eq!(35, seen.len());
+            assert!(seen.contains(&PathBuf::from("0/0")));
+            assert!(!seen.contains(&PathBuf::from("0/0/file.txt")));
+            assert!(seen.contains(&PathBuf::from("3/3/file.txt")));
+        }
+    }
+
+    #[test]
+    fn walk_parallel_quit() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = setup(&tmp_dir);
+
+        let count = AtomicUsize::new(0);
+        tmp_abs
+            .walk_parallel_with(WalkOptions::new().threads(1), |_| {
+                count.fetch_add(1, Ordering::SeqCst);
+                WalkState::Quit
+            })
+            .unwrap();
+        assert_eq!(1, count.load(Ordering::SeqCst));
+
+        let count = AtomicUsize::new(0);
+        tmp_abs
+            .walk_parallel(|_| {
+                if count.fetch_add(1, Ordering::SeqCst) == 10 {
+                    WalkState::Quit
+                } else {
+                    WalkState::Continue
+                }
+            })
+            .unwrap();
+        assert!(count.load(Ordering::SeqCst) < 36);
+    }
+
+    #[test]
+    fn walk_parallel_panic() {
+        use std::panic;
+
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = setup(&tmp_dir);
+
+        let result = panic::catch_unwind(|| {
+            tmp_abs
+                .walk_parallel_with(WalkOptions::new().threads(4), |entry| {
+                    if entry.unwrap().ends_with("1/1") {
+                        panic!("visitor panicked");
+                    }
+                    WalkState::Continue
+                })
+                .unwrap();
+        });
+        assert!(result.is_err());
+    }
+}
@@ -13,7 +13,7 @@ use std::io;
 use std_prelude::*;
 
 use super::{Error, Result};
-use super::PathFile;
+use super::{PathArc, PathFile};
 use super::open::FileOpen;
 
 /// A write-only file handle with `path()` attached and improved error messages. Contains only the
@@ -67,6 +67,23 @@ impl FileWrite {
         FileWrite::open(path, options)
     }
 
+    /// Open the file in write-only mode, creating it. This fails with
+    /// `io::ErrorKind::AlreadyExists` if the path exists.
+    ///
+    /// The file is created atomically (with `O_EXCL`), so only one of several processes trying
+    /// to create it at the same time succeeds.
+    pub fn create_new<P: AsRef<Path>>(path: P) -> Result<FileWrite> {
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .map_err(|err| Error::new(err, "creating", PathArc::new(&path)))?;
+        Ok(FileWrite(FileOpen {
+            path: PathFile::new(path)?,
+            file,
+        }))
+    }
+
     /// Open the file for appending, creating it if it doesn't exist.
     pub fn append<P: AsRef<Path>>(path: P) -> Result<FileWrite> {
         let mut options = fs::OpenOptions::new();
@@ -167,32 +184,26 @@ impl fmt::Debug for FileWrite {
 
 impl io::Write for FileWrite {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.0.file.write(buf).map_err(|err| {
-            io::Error::new(
-                err.kind(),
-                format!("{} when writing to {}", err, self.path().display()),
-            )
-        })
+        self.0
+            .file
+            .write(buf)
+            .map_err(|err| Error::new(err, "writing to", self.path.clone().into()).into())
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        self.0.file.flush().map_err(|err| {
-            io::Error::new(
-                err.kind(),
-                format!("{} when flushing {}", err, self.path().display()),
-            )
-        })
+        self.0
+            .file
+            .flush()
+            .map_err(|err| Error::new(err, "flushing", self.path.clone().into()).into())
     }
 }
 
 impl io::Seek for FileWrite {
     fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
-        self.0.file.seek(pos).map_err(|err| {
-            io::Error::new(
-                err.kind(),
-                format!("{} seeking {}", err, self.path().display()),
-            )
-        })
+        self.0
+            .file
+            .seek(pos)
+            .map_err(|err| Error::new(err, "seeking", self.path.clone().into()).into())
     }
 }
 
@@ -203,3 +214,26 @@ impl Deref for FileWrite {
         &self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use tempdir::TempDir;
+
+    use super::super::{FileWrite, PathDir, PathFile};
+
+    #[test]
+    fn create_new() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let foo = tmp_abs.join("foo.txt");
+
+        let mut write = FileWrite::create_new(&foo).unwrap();
+        write.write_str("foo").unwrap();
+        let err = FileWrite::create_new(&foo).unwrap_err();
+        assert_eq!(io::ErrorKind::AlreadyExists, err.io_error().kind());
+        assert_eq!(foo.as_path(), err.path());
+        assert!(err.to_string().starts_with("path already exists"));
+        assert_eq!("foo", PathFile::new(&foo).unwrap().read_string().unwrap());
+    }
+}